@@ -2,7 +2,7 @@
 extern crate lanta;
 
 use lanta::layout::*;
-use lanta::{cmd, Lanta, ModKey, Result};
+use lanta::{cmd, Borders, KeyTrigger, Lanta, ModKey, MouseAction, Result};
 
 macro_rules! spawn {
     ($cmd:expr) => (::lanta::cmd::lazy::spawn(::std::process::Command::new($cmd)));
@@ -24,11 +24,15 @@ fn main() -> Result<()> {
     #[rustfmt::skip]
     let mut keys = keys![
         ([modkey], XK_w, cmd::lazy::close_focused_window()),
+        ([modkey, shift], XK_q, cmd::lazy::quit()),
+        ([modkey, shift], XK_r, cmd::lazy::restart()),
         ([modkey], XK_j, cmd::lazy::focus_next()),
         ([modkey], XK_k, cmd::lazy::focus_previous()),
         ([modkey, shift], XK_j, cmd::lazy::shuffle_next()),
         ([modkey, shift], XK_k, cmd::lazy::shuffle_previous()),
+        ([modkey, shift], XK_Return, cmd::lazy::swap_master()),
         ([modkey], XK_Tab, cmd::lazy::layout_next()),
+        ([modkey, shift], XK_Tab, cmd::lazy::layout_previous()),
 
         ([modkey], XK_Return, spawn!("urxvt")),
         ([modkey], XK_c, spawn!("firefox")),
@@ -39,7 +43,7 @@ fn main() -> Result<()> {
         ([], XF86XK_MonBrightnessUp, spawn!("xbacklight", "-inc", "10")),
         ([], XF86XK_MonBrightnessDown, spawn!("xbacklight", "-dec", "10")),
         ([], XF86XK_AudioPrev, spawn!("playerctl", "previous")),
-        ([], XF86XK_AudioPlay, spawn!("playerctl", "play-pause")),
+        ([], XF86XK_AudioPlay, spawn!("playerctl", "play-pause"), KeyTrigger::Release),
         ([], XF86XK_AudioNext, spawn!("playerctl", "next")),
         ([], XF86XK_AudioRaiseVolume, spawn!("amixer", "-q", "set", "Master", "5%+")),
         ([], XF86XK_AudioLowerVolume, spawn!("amixer", "-q", "set", "Master", "5%-")),
@@ -64,7 +68,19 @@ fn main() -> Result<()> {
         ]
     };
 
-    Lanta::new(keys, groups, &layouts)?.run();
+    let borders = Borders {
+        width: 2,
+        focused_color: 0x005f_87af,
+        unfocused_color: 0x0030_3030,
+    };
+
+    let mut wm = Lanta::new(keys, groups, &layouts, borders)?;
+    wm.set_mouse_bindings(vec![
+        (vec![modkey], 1, MouseAction::Move),
+        (vec![modkey], 3, MouseAction::Resize),
+    ]);
+    wm.on_startup(spawn!("change-wallpaper"));
+    wm.run();
 
     Ok(())
 }
@@ -2,9 +2,57 @@ use std::rc::Rc;
 
 use crate::Lanta;
 use crate::Result;
+use crate::Viewport;
 
 pub type Command = Rc<dyn Fn(&mut Lanta) -> Result<()>>;
 
+/// A direction on the screen, e.g. for moving focus spatially.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl Direction {
+    /// Returns the index of the rectangle nearest to `from` in this direction,
+    /// or `None` if there are none in this direction.
+    ///
+    /// A rectangle is in this direction if its center is. Rectangles which are
+    /// further off to the side count as further away than those which are
+    /// directly in line.
+    pub(crate) fn nearest(self, from: &Viewport, candidates: &[Viewport]) -> Option<usize> {
+        fn center(rect: &Viewport) -> (i64, i64) {
+            (
+                i64::from(rect.x) + i64::from(rect.width) / 2,
+                i64::from(rect.y) + i64::from(rect.height) / 2,
+            )
+        }
+
+        let (from_x, from_y) = center(from);
+        candidates
+            .iter()
+            .enumerate()
+            .filter_map(|(i, rect)| {
+                let (x, y) = center(rect);
+                let (along, across) = match self {
+                    Direction::Left => (from_x - x, y - from_y),
+                    Direction::Right => (x - from_x, y - from_y),
+                    Direction::Up => (from_y - y, x - from_x),
+                    Direction::Down => (y - from_y, x - from_x),
+                };
+                if along > 0 {
+                    Some((i, along + (2 * across.abs())))
+                } else {
+                    None
+                }
+            })
+            .min_by_key(|&(_, distance)| distance)
+            .map(|(i, _)| i)
+    }
+}
+
 /// Lazy-functions which return a `Command` to do the requested action.
 // TODO: Consider offering non-lazy versions and then having simple lazy
 // wrappers for them.
@@ -16,7 +64,11 @@ pub mod lazy {
 
     use failure::ResultExt;
 
-    use super::Command;
+    use crate::groups::Layer;
+
+    use super::{Command, Direction};
+
+    const MASTER_RATIO_STEP: f32 = 0.05;
 
     /// Closes the currently focused window.
     pub fn close_focused_window() -> Command {
@@ -26,10 +78,43 @@ pub mod lazy {
         })
     }
 
+    /// Stops lanta, handing all windows back unmanaged.
+    pub fn quit() -> Command {
+        Rc::new(|wm| {
+            wm.quit();
+            Ok(())
+        })
+    }
+
+    /// Restarts lanta in place (e.g. to pick up a new config), keeping all of
+    /// the windows open.
+    pub fn restart() -> Command {
+        Rc::new(|wm| {
+            wm.restart();
+            Ok(())
+        })
+    }
+
     /// Moves the focus to the next window in the current group's stack.
     pub fn focus_next() -> Command {
         Rc::new(|ref mut wm| {
-            wm.group_mut().focus_next();
+            if !wm.is_focus_locked() {
+                wm.group_mut().focus_next();
+                wm.warp_pointer_to_focused();
+            }
+            Ok(())
+        })
+    }
+
+    /// Focuses the window at the given position in the current group's stack,
+    /// where the first window is number 1 (so that it can be bound to the
+    /// number keys).
+    pub fn focus_window_number(number: usize) -> Command {
+        Rc::new(move |wm| {
+            if !wm.is_focus_locked() && number > 0 {
+                wm.group_mut().focus_index(number - 1);
+                wm.warp_pointer_to_focused();
+            }
             Ok(())
         })
     }
@@ -37,7 +122,125 @@ pub mod lazy {
     /// Moves the focus to the previous window in the current group's stack.
     pub fn focus_previous() -> Command {
         Rc::new(|ref mut wm| {
-            wm.group_mut().focus_previous();
+            if !wm.is_focus_locked() {
+                wm.group_mut().focus_previous();
+                wm.warp_pointer_to_focused();
+            }
+            Ok(())
+        })
+    }
+
+    /// Focuses the window which has been urgent (e.g. a chat window which
+    /// was sent a message) for longest, switching to the group containing it.
+    pub fn focus_urgent() -> Command {
+        Rc::new(|wm| {
+            wm.focus_urgent();
+            Ok(())
+        })
+    }
+
+    /// Reads part of a window's title from the keyboard, then focuses the
+    /// first window (in any group) whose title contains it. Enter confirms and
+    /// Escape cancels.
+    pub fn focus_by_title() -> Command {
+        Rc::new(|wm| wm.start_focus_by_title())
+    }
+
+    /// Moves the focus to the nearest window in the given direction, by where
+    /// the windows are on the screen rather than their order in the stack.
+    pub fn focus_direction(direction: Direction) -> Command {
+        Rc::new(move |wm| {
+            if !wm.is_focus_locked() {
+                wm.group_mut().focus_direction(direction);
+                wm.warp_pointer_to_focused();
+            }
+            Ok(())
+        })
+    }
+
+    /// Focuses the window in the current group that takes up the most space
+    /// on the screen.
+    pub fn focus_largest() -> Command {
+        Rc::new(|wm| {
+            if !wm.is_focus_locked() {
+                wm.group_mut().focus_largest();
+                wm.warp_pointer_to_focused();
+            }
+            Ok(())
+        })
+    }
+
+    /// Focuses the window in the current group that takes up the least space
+    /// on the screen.
+    pub fn focus_smallest() -> Command {
+        Rc::new(|wm| {
+            if !wm.is_focus_locked() {
+                wm.group_mut().focus_smallest();
+                wm.warp_pointer_to_focused();
+            }
+            Ok(())
+        })
+    }
+
+    /// Toggles a lock which stops the focus from changing, whether by the
+    /// pointer or by commands.
+    pub fn toggle_focus_lock() -> Command {
+        Rc::new(|wm| {
+            wm.toggle_focus_lock();
+            Ok(())
+        })
+    }
+
+    /// Toggles whether hovering a window with the pointer raises it, as well
+    /// as focusing it.
+    pub fn toggle_focus_raises() -> Command {
+        Rc::new(|wm| {
+            wm.toggle_focus_raises();
+            Ok(())
+        })
+    }
+
+    /// Focuses the window that the focused window is transient for, e.g. the
+    /// main window of a dialog.
+    pub fn focus_parent() -> Command {
+        Rc::new(|ref mut wm| {
+            wm.focus_parent();
+            Ok(())
+        })
+    }
+
+    /// Focuses a window that is transient for the focused window, e.g. a
+    /// dialog it has opened.
+    pub fn focus_child() -> Command {
+        Rc::new(|ref mut wm| {
+            wm.focus_child();
+            Ok(())
+        })
+    }
+
+    /// Hides the focused window, leaving it in the current group but out of the
+    /// layout.
+    pub fn toggle_hide_focused() -> Command {
+        Rc::new(|ref mut wm| {
+            wm.group_mut().toggle_hide_focused();
+            Ok(())
+        })
+    }
+
+    /// Shows all hidden windows in the current group.
+    pub fn unhide_all() -> Command {
+        Rc::new(|ref mut wm| {
+            wm.group_mut().unhide_all();
+            Ok(())
+        })
+    }
+
+    /// Pins the focused window to a pane taking up `ratio` of the screen's
+    /// width, laying out the other windows beside it. Toggling again releases
+    /// the window back into the layout.
+    pub fn toggle_side_pane(ratio: f32) -> Command {
+        Rc::new(move |wm| {
+            wm.group_mut().toggle_side_pane(ratio);
             Ok(())
         })
     }
@@ -60,6 +263,50 @@ pub mod lazy {
         })
     }
 
+    /// Moves the focused window one position towards the front of the current
+    /// group's stack, stopping at the front.
+    pub fn move_up() -> Command {
+        Rc::new(|ref mut wm| {
+            wm.group_mut().move_up();
+            Ok(())
+        })
+    }
+
+    /// Moves the focused window one position towards the end of the current
+    /// group's stack, stopping at the end.
+    pub fn move_down() -> Command {
+        Rc::new(|ref mut wm| {
+            wm.group_mut().move_down();
+            Ok(())
+        })
+    }
+
+    /// Swaps the focused window with the first window in the current group's
+    /// stack, promoting it to the master area of a master/stack layout.
+    pub fn swap_master() -> Command {
+        Rc::new(|ref mut wm| {
+            wm.group_mut().swap_master();
+            Ok(())
+        })
+    }
+
+    /// Moves the focused window to the front of the current group's stack
+    /// (e.g. into the master area of a master/stack layout).
+    pub fn move_to_front() -> Command {
+        Rc::new(|ref mut wm| {
+            wm.group_mut().move_to_front();
+            Ok(())
+        })
+    }
+
+    /// Moves the focused window to the end of the current group's stack.
+    pub fn move_to_back() -> Command {
+        Rc::new(|ref mut wm| {
+            wm.group_mut().move_to_back();
+            Ok(())
+        })
+    }
+
     /// Cycles to the next layout of the current group.
     pub fn layout_next() -> Command {
         Rc::new(|ref mut wm| {
@@ -68,6 +315,139 @@ pub mod lazy {
         })
     }
 
+    /// Cycles to the previous layout of the current group.
+    pub fn layout_previous() -> Command {
+        Rc::new(|ref mut wm| {
+            wm.group_mut().layout_previous();
+            Ok(())
+        })
+    }
+
+    /// Switches every group to the layout specified by name.
+    pub fn set_layout_all(name: &'static str) -> Command {
+        Rc::new(move |wm| {
+            wm.set_layout_all(name);
+            Ok(())
+        })
+    }
+
+    /// Flips the current group's layout between tiling vertically and
+    /// horizontally.
+    pub fn toggle_tile_orientation() -> Command {
+        Rc::new(|ref mut wm| {
+            wm.group_mut().toggle_layout_orientation();
+            Ok(())
+        })
+    }
+
+    /// Rotates the current group's layout by 90°, so that e.g. windows tiled
+    /// vertically are tiled horizontally and a master column on the left
+    /// becomes a row along the top. Useful on portrait monitors.
+    pub fn rotate_layout() -> Command {
+        Rc::new(|wm| {
+            wm.group_mut().toggle_layout_rotation();
+            Ok(())
+        })
+    }
+
+    /// Moves the master area of the current group's layout (e.g.
+    /// `MasterStackLayout`) to the other side of the screen.
+    pub fn toggle_master_side() -> Command {
+        Rc::new(|wm| {
+            wm.group_mut().toggle_layout_master_side();
+            Ok(())
+        })
+    }
+
+    /// Toggles whether new windows in the current group split the focused
+    /// window vertically or horizontally.
+    pub fn toggle_split_direction() -> Command {
+        Rc::new(|wm| {
+            wm.group_mut().toggle_split_direction();
+            Ok(())
+        })
+    }
+
+    /// Grows the gap around windows in the current group's layout by `step`
+    /// pixels.
+    pub fn increase_gap(step: u32) -> Command {
+        Rc::new(move |wm| {
+            wm.group_mut().adjust_layout_gap(step as i32);
+            Ok(())
+        })
+    }
+
+    /// Shrinks the gap around windows in the current group's layout by `step`
+    /// pixels.
+    pub fn decrease_gap(step: u32) -> Command {
+        Rc::new(move |wm| {
+            wm.group_mut().adjust_layout_gap(-(step as i32));
+            Ok(())
+        })
+    }
+
+    /// Switches the current group to the named preset's layout, with the
+    /// preset's parameters (e.g. gap and master ratio). Presets are set with
+    /// `Lanta::set_layout_presets()`.
+    pub fn apply_preset(name: &'static str) -> Command {
+        Rc::new(move |wm| wm.apply_preset(name))
+    }
+
+    /// Applies each of the named presets in turn, each time the command is
+    /// run. With two presets, this toggles between them. Each group keeps its
+    /// own place in the cycle.
+    pub fn cycle_presets(names: Vec<&'static str>) -> Command {
+        Rc::new(move |wm| wm.cycle_presets(&names))
+    }
+
+    /// Switches the current group back to its default layout.
+    pub fn reset_layout() -> Command {
+        Rc::new(|wm| {
+            wm.group_mut().reset_layout();
+            Ok(())
+        })
+    }
+
+    /// Grows the master area of the current group's layout by 5% of the
+    /// width.
+    pub fn increase_master_ratio() -> Command {
+        Rc::new(|wm| {
+            wm.group_mut().adjust_layout_master_ratio(MASTER_RATIO_STEP);
+            Ok(())
+        })
+    }
+
+    /// Shrinks the master area of the current group's layout by 5% of the
+    /// width.
+    pub fn decrease_master_ratio() -> Command {
+        Rc::new(|wm| {
+            wm.group_mut()
+                .adjust_layout_master_ratio(-MASTER_RATIO_STEP);
+            Ok(())
+        })
+    }
+
+    /// Toggles between showing the desktop and the current group's windows.
+    pub fn toggle_show_desktop() -> Command {
+        Rc::new(|ref mut wm| {
+            wm.toggle_show_desktop();
+            Ok(())
+        })
+    }
+
+    /// Manages any mapped windows which have slipped through unmanaged.
+    pub fn rescan_windows() -> Command {
+        Rc::new(|ref mut wm| wm.rescan_windows())
+    }
+
+    /// Toggles inhibiting the X screen saver, e.g. while watching a video.
+    pub fn toggle_idle_inhibit() -> Command {
+        Rc::new(|wm| {
+            wm.toggle_idle_inhibit();
+            Ok(())
+        })
+    }
+
     /// Spawns the specified command.
     ///
     /// The returned `Command` will spawn the `Command` each time it is called.
@@ -83,6 +463,117 @@ pub mod lazy {
         })
     }
 
+    /// Makes the focused window float, so that the layout skips it and it
+    /// keeps its own geometry, or returns it to the layout.
+    pub fn toggle_float() -> Command {
+        Rc::new(|wm| {
+            wm.group_mut().toggle_float_focused();
+            Ok(())
+        })
+    }
+
+    /// Moves the focus from the tiled windows to the floating windows, or back
+    /// again, returning to the window last focused in each.
+    pub fn toggle_focus_layer() -> Command {
+        Rc::new(|wm| {
+            if !wm.is_focus_locked() {
+                wm.group_mut().toggle_focus_layer();
+                wm.warp_pointer_to_focused();
+            }
+            Ok(())
+        })
+    }
+
+    /// Returns every floating window in the active group to the layout.
+    pub fn tile_all_floats() -> Command {
+        Rc::new(|wm| {
+            wm.group_mut().tile_all_floating();
+            Ok(())
+        })
+    }
+
+    /// Keeps the focused window at its current size and position while the
+    /// other windows are tiled in the space around it, or returns it to the
+    /// layout if it's already pinned.
+    pub fn toggle_pin_size() -> Command {
+        Rc::new(|wm| {
+            wm.group_mut().toggle_pin_size_focused();
+            Ok(())
+        })
+    }
+
+    /// Keeps the focused window above other windows, or returns it to the
+    /// normal layer if it already is.
+    pub fn toggle_above() -> Command {
+        Rc::new(|wm| {
+            wm.group_mut().toggle_focused_layer(Layer::Above);
+            Ok(())
+        })
+    }
+
+    /// Keeps the focused window below other windows, or returns it to the
+    /// normal layer if it already is.
+    pub fn toggle_below() -> Command {
+        Rc::new(|wm| {
+            wm.group_mut().toggle_focused_layer(Layer::Below);
+            Ok(())
+        })
+    }
+
+    /// Reloads the rules file, which places new windows onto groups.
+    pub fn reload_rules() -> Command {
+        Rc::new(|wm| wm.reload_rules())
+    }
+
+    /// Logs details of the X server, screen and viewport, e.g. for bug
+    /// reports.
+    pub fn diagnostics() -> Command {
+        Rc::new(|wm| {
+            wm.log_diagnostics();
+            Ok(())
+        })
+    }
+
+    /// Pins the focused window (e.g. a system monitor) so that it's shown on
+    /// every group and left out of layouts, or unpins it into the current
+    /// group if it's already pinned.
+    pub fn toggle_utility_pin() -> Command {
+        Rc::new(|wm| {
+            wm.toggle_utility_pin();
+            Ok(())
+        })
+    }
+
+    /// Hides or shows any docks (e.g. status bars).
+    pub fn toggle_docks() -> Command {
+        Rc::new(|wm| {
+            wm.toggle_docks();
+            Ok(())
+        })
+    }
+
+    /// Toggles a distraction-free mode: hides docks, switches the current
+    /// group to `layout` and disables focus-follows-mouse. Toggling it off
+    /// restores each of these.
+    pub fn toggle_zen(layout: &'static str) -> Command {
+        Rc::new(move |wm| {
+            wm.toggle_zen(layout);
+            Ok(())
+        })
+    }
+
+    /// Saves the groups, their layouts and windows to the named session, under
+    /// `$XDG_DATA_HOME/lanta/sessions/`.
+    pub fn save_session(name: &'static str) -> Command {
+        Rc::new(move |wm| wm.save_session(name))
+    }
+
+    /// Restores the named session, re-launching its programs and routing
+    /// their windows to the groups they were saved in.
+    pub fn restore_session(name: &'static str) -> Command {
+        Rc::new(move |wm| wm.restore_session(name))
+    }
+
     /// Switches to the group specified by name.
     pub fn switch_group(name: &'static str) -> Command {
         Rc::new(move |wm| {
@@ -91,11 +582,112 @@ pub mod lazy {
         })
     }
 
-    /// Moves the focused window on the active group to another group.
-    pub fn move_window_to_group(name: &'static str) -> Command {
+    /// Switches to the group at the given position in the list of groups,
+    /// starting at 0.
+    pub fn switch_group_index(index: usize) -> Command {
         Rc::new(move |wm| {
-            wm.move_focused_to_group(name);
+            wm.switch_group_index(index);
             Ok(())
         })
     }
+
+    /// Moves the focused window on the active group to another group.
+    pub fn move_window_to_group(name: &'static str) -> Command {
+        Rc::new(move |wm| wm.move_focused_to_group(name))
+    }
+
+    /// Moves the focused window on the active group to the next group,
+    /// wrapping around, without switching to it.
+    pub fn send_to_next_group() -> Command {
+        Rc::new(|wm| wm.send_focused_to_adjacent_group(true, false))
+    }
+
+    /// Moves the focused window on the active group to the previous group,
+    /// wrapping around, without switching to it.
+    pub fn send_to_previous_group() -> Command {
+        Rc::new(|wm| wm.send_focused_to_adjacent_group(false, false))
+    }
+
+    /// Moves the focused window on the active group to the next group,
+    /// wrapping around, and switches to that group.
+    ///
+    /// Pressed repeatedly, this carries a window across the groups one at a
+    /// time.
+    pub fn follow_to_next_group() -> Command {
+        Rc::new(|wm| wm.send_focused_to_adjacent_group(true, true))
+    }
+
+    /// Moves the focused window on the active group to the previous group,
+    /// wrapping around, and switches to that group.
+    pub fn follow_to_previous_group() -> Command {
+        Rc::new(|wm| wm.send_focused_to_adjacent_group(false, true))
+    }
+
+    /// Moves the focused window on the active group to the group with the
+    /// fewest windows.
+    pub fn move_to_emptiest_group() -> Command {
+        Rc::new(|wm| wm.move_focused_to_emptiest_group())
+    }
+
+    /// Shows a small thumbnail of the focused window in the corner of the
+    /// screen, which keeps showing the window's contents while working in
+    /// other windows. Hides the thumbnail if it's already shown.
+    ///
+    /// Requires the `pip` feature, and the XCOMPOSITE, DAMAGE and RENDER
+    /// extensions.
+    #[cfg(feature = "pip")]
+    pub fn toggle_pip() -> Command {
+        Rc::new(|wm| wm.toggle_pip())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rect(x: u32, y: u32, width: u32, height: u32) -> Viewport {
+        Viewport {
+            x,
+            y,
+            width,
+            height,
+            monitor: 0,
+        }
+    }
+
+    #[test]
+    fn test_nearest_direction() {
+        // A master window on the left, with two windows stacked on the right.
+        let master = rect(0, 0, 500, 600);
+        let top = rect(500, 0, 500, 300);
+        let bottom = rect(500, 300, 500, 300);
+        let windows = [master, top, bottom];
+
+        assert_eq!(Direction::Right.nearest(&master, &windows), Some(1));
+        assert_eq!(Direction::Left.nearest(&top, &windows), Some(0));
+        assert_eq!(Direction::Down.nearest(&top, &windows), Some(2));
+        assert_eq!(Direction::Up.nearest(&bottom, &windows), Some(1));
+    }
+
+    #[test]
+    fn test_nearest_direction_none() {
+        let left = rect(0, 0, 500, 600);
+        let right = rect(500, 0, 500, 600);
+        let windows = [left, right];
+
+        assert_eq!(Direction::Left.nearest(&left, &windows), None);
+        assert_eq!(Direction::Up.nearest(&left, &windows), None);
+        assert_eq!(Direction::Down.nearest(&right, &windows), None);
+    }
+
+    #[test]
+    fn test_nearest_direction_prefers_in_line() {
+        let from = rect(0, 0, 100, 100);
+        let in_line = rect(400, 0, 100, 100);
+        let diagonal = rect(200, 300, 100, 100);
+        assert_eq!(
+            Direction::Right.nearest(&from, &[diagonal, in_line]),
+            Some(1)
+        );
+    }
 }
@@ -1,14 +1,20 @@
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::HashMap;
 use std::rc::Rc;
 
 use super::Viewport;
-use crate::layout::Layout;
+use crate::cmd::{Command, Direction};
+use crate::layout::{self, Layout, LayoutPreset, Orientation, SidePane};
 use crate::stack::Stack;
-use crate::x::{Connection, WindowId};
+use crate::x::{Connection, WindowId, WindowState, WmState};
 
 #[derive(Clone)]
 pub struct GroupBuilder {
     name: String,
     default_layout: String,
+    keep_mapped_when_inactive: bool,
+    on_empty: Option<Command>,
 }
 
 impl GroupBuilder {
@@ -20,10 +26,36 @@ impl GroupBuilder {
         GroupBuilder {
             name: name.into(),
             default_layout: default_layout.into(),
+            keep_mapped_when_inactive: false,
+            on_empty: None,
         }
     }
 
-    pub fn build(self, connection: Rc<Connection>, layouts: Vec<Box<dyn Layout>>) -> Group {
+    /// Keeps the group's windows mapped (but moved off-screen) while the group
+    /// is inactive, rather than unmapping them.
+    ///
+    /// Useful for applications which stop working when unmapped, e.g. video
+    /// calls which stop rendering.
+    pub fn keep_mapped_when_inactive(mut self, keep_mapped: bool) -> GroupBuilder {
+        self.keep_mapped_when_inactive = keep_mapped;
+        self
+    }
+
+    /// Runs `command` whenever the group's last window is removed (e.g. to
+    /// clean up after a scratch group).
+    ///
+    /// The command isn't run for groups which start out empty.
+    pub fn on_empty(mut self, command: Command) -> GroupBuilder {
+        self.on_empty = Some(command);
+        self
+    }
+
+    pub fn build(
+        self,
+        connection: Rc<Connection>,
+        layouts: Vec<Box<dyn Layout>>,
+        borders: Borders,
+    ) -> Group {
         let mut layouts_stack = Stack::from(layouts);
         layouts_stack.focus(|layout| layout.name() == self.default_layout);
 
@@ -32,18 +64,130 @@ impl GroupBuilder {
             name: self.name.clone(),
             active: false,
             stack: Stack::new(),
+            hidden: Vec::new(),
+            mapped: RefCell::new(Vec::new()),
+            split_direction: None,
+            rotated: false,
+            showing_desktop: false,
+            side_pane: None,
+            demanding_attention: Vec::new(),
+            layout_suppressed: false,
+            layers: Vec::new(),
+            floating: Vec::new(),
+            float_geometry: HashMap::new(),
+            last_tiled_focus: None,
+            last_floating_focus: None,
+            size_pinned: Vec::new(),
+            fullscreen: Vec::new(),
+            docks: Vec::new(),
             layouts: layouts_stack,
+            preset: None,
+            default_layout: self.default_layout,
+            borders,
+            keep_mapped_when_inactive: self.keep_mapped_when_inactive,
+            on_empty: self.on_empty,
             viewport: Viewport::default(),
         }
     }
 }
 
+/// The borders drawn around windows, which show which window is focused.
+///
+/// Colors are `0xRRGGBB` pixel values. The default is to draw no borders.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Borders {
+    pub width: u32,
+    pub focused_color: u32,
+    pub unfocused_color: u32,
+}
+
+/// The stacking layer of a window. Windows are kept above all windows in lower
+/// layers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Layer {
+    Below,
+    Normal,
+    Above,
+}
+
+impl Layer {
+    fn window_state(self) -> Option<WindowState> {
+        match self {
+            Layer::Below => Some(WindowState::Below),
+            Layer::Normal => None,
+            Layer::Above => Some(WindowState::Above),
+        }
+    }
+}
+
+/// A read-only summary of a group, e.g. for rendering a pager.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GroupSummary {
+    pub name: String,
+    /// Whether the group is the one currently shown.
+    pub active: bool,
+    /// Whether the group has any windows.
+    pub occupied: bool,
+    pub window_count: usize,
+    /// Whether any of the group's windows are demanding attention.
+    pub demands_attention: bool,
+}
+
 pub struct Group {
     name: String,
     connection: Rc<Connection>,
     active: bool,
     stack: Stack<WindowId>,
+    /// Windows in the stack which have been hidden, and so are left out of
+    /// the layout.
+    hidden: Vec<WindowId>,
+    /// Windows in the stack which are currently mapped, so that we only map
+    /// or unmap windows whose state changes, rather than on every layout.
+    mapped: RefCell<Vec<WindowId>>,
+    /// How new windows split the focused window, once set by
+    /// `toggle_split_direction()`.
+    split_direction: Option<Orientation>,
+    /// Whether the layout's placements are rotated by 90°, e.g. so that a
+    /// master column on the left becomes a row along the top.
+    rotated: bool,
+    /// Whether all windows are unmapped to show the desktop.
+    showing_desktop: bool,
+    /// A window pinned to a pane beside the layout, if any.
+    side_pane: Option<SidePane>,
+    /// Windows which have asked for the user's attention since they were last
+    /// focused.
+    demanding_attention: Vec<WindowId>,
+    /// Set while a batch of mutations is in progress, see
+    /// `with_suppressed_layout()`.
+    layout_suppressed: bool,
+    /// The layer of each window not in `Layer::Normal`.
+    layers: Vec<(WindowId, Layer)>,
+    /// Windows which the layout skips, and the geometry they keep instead.
+    floating: Vec<(WindowId, Viewport)>,
+    /// The geometry each window last had while floating, so that it returns
+    /// there when floated again after being tiled.
+    float_geometry: HashMap<WindowId, Viewport>,
+    /// The window that was focused in each of the tiled and floating layers
+    /// when `toggle_focus_layer()` last left them.
+    last_tiled_focus: Option<WindowId>,
+    last_floating_focus: Option<WindowId>,
+    /// Tiled windows which keep the geometry they had when their size was
+    /// pinned, with the layout tiling the other windows around them.
+    size_pinned: Vec<(WindowId, Viewport)>,
+    /// Windows which cover the whole screen, including any docks.
+    fullscreen: Vec<WindowId>,
+    /// The docks, which are kept above the group's windows (other than
+    /// fullscreen ones). They aren't members of the group.
+    docks: Vec<WindowId>,
     layouts: Stack<Box<dyn Layout>>,
+    /// The name of the preset last applied with `apply_preset()`, if any.
+    preset: Option<String>,
+    /// The name of the layout the group was built with.
+    default_layout: String,
+    borders: Borders,
+    keep_mapped_when_inactive: bool,
+    /// Run when the group's last window is removed.
+    on_empty: Option<Command>,
     viewport: Viewport,
 }
 
@@ -52,6 +196,26 @@ impl Group {
         &self.name
     }
 
+    /// Returns the number of windows in the group.
+    pub fn len(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Returns whether the group has no windows.
+    pub fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    pub fn summary(&self) -> GroupSummary {
+        GroupSummary {
+            name: self.name.clone(),
+            active: self.active,
+            occupied: !self.stack.is_empty(),
+            window_count: self.stack.len(),
+            demands_attention: !self.demanding_attention.is_empty(),
+        }
+    }
+
     pub fn activate(&mut self, viewport: Viewport) {
         info!("Activating group: {}", self.name());
         self.active = true;
@@ -59,6 +223,15 @@ impl Group {
         self.perform_layout();
     }
 
+    /// Returns the command to run when the group's last window is removed.
+    pub fn on_empty(&self) -> Option<Command> {
+        self.on_empty.clone()
+    }
+
+    pub fn viewport(&self) -> &Viewport {
+        &self.viewport
+    }
+
     pub fn update_viewport(&mut self, viewport: Viewport) {
         self.viewport = viewport;
         self.perform_layout();
@@ -66,40 +239,213 @@ impl Group {
 
     pub fn deactivate(&mut self) {
         info!("Deactivating group: {}", self.name());
+        if self.keep_mapped_when_inactive {
+            self.move_off_screen();
+        } else {
+            for window_id in self.stack.iter() {
+                self.unmap_window(window_id);
+            }
+        }
+        self.active = false;
+        self.showing_desktop = false;
+    }
+
+    /// Moves the windows just past the right edge of the screen, and below any
+    /// other windows, leaving them mapped. They're put back in place by the
+    /// next layout.
+    fn move_off_screen(&self) {
         for window_id in self.stack.iter() {
+            self.move_window_off_screen(window_id);
+        }
+    }
+
+    fn move_window_off_screen(&self, window_id: &WindowId) {
+        let root = self.connection.root_window_id();
+        let screen_width = match self.connection.get_window_rect(root) {
+            Some((_, _, width, _)) => width,
+            None => return,
+        };
+        if let Some((_, y, width, height)) = self.connection.get_window_rect(window_id) {
+            self.connection
+                .configure_window_exact(window_id, screen_width, y, width, height);
+            self.connection.restack_window(window_id, false);
+        }
+    }
+
+    /// Maps any windows added since the group was deactivated off-screen,
+    /// alongside the windows `deactivate()` moved there.
+    fn map_off_screen(&self) {
+        for window_id in self.stack.iter() {
+            if self.hidden.contains(window_id) || self.mapped.borrow().contains(window_id) {
+                continue;
+            }
             self.connection.disable_window_tracking(window_id);
-            self.connection.unmap_window(window_id);
+            self.move_window_off_screen(window_id);
+            self.map_window(window_id);
             self.connection.enable_window_tracking(window_id);
         }
-        self.active = false;
+    }
+
+    /// Runs `f`, deferring any layout it causes until it completes, and then
+    /// performing a single layout.
+    ///
+    /// Useful for commands which make several changes to the group in turn,
+    /// to avoid laying out (and flickering) after each one.
+    pub fn with_suppressed_layout<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut Group) -> R,
+    {
+        let suppressed = self.layout_suppressed;
+        self.layout_suppressed = true;
+        let result = f(self);
+        self.layout_suppressed = suppressed;
+        self.perform_layout();
+        result
+    }
+
+    /// Defers any layout until `resume_layout()` is called.
+    ///
+    /// Prefer `with_suppressed_layout()`, unless the changes can't be made
+    /// from a closure (e.g. because they span several groups).
+    pub(crate) fn suppress_layout(&mut self) {
+        self.layout_suppressed = true;
+    }
+
+    /// Performs any layout deferred by `suppress_layout()`.
+    pub(crate) fn resume_layout(&mut self) {
+        self.layout_suppressed = false;
+        self.perform_layout();
     }
 
     fn perform_layout(&mut self) {
+        if self.layout_suppressed {
+            return;
+        }
         if !self.active {
+            if self.keep_mapped_when_inactive {
+                self.map_off_screen();
+            }
             return;
         }
 
+        if self.showing_desktop {
+            self.connection.focus_nothing();
+            return;
+        }
+
+        let hidden = &self.hidden;
+        let stack = self.stack.filtered(|window_id| !hidden.contains(window_id));
+        let floating = &self.floating;
+        let fullscreen = &self.fullscreen;
+        let tiled = stack.filtered(|window_id| {
+            !floating.iter().any(|(w, _)| w == window_id) && !fullscreen.contains(window_id)
+        });
+
+        // Windows with a pinned size keep their geometry, and the layout
+        // only has the space left around them.
+        let size_pinned: Vec<_> = self
+            .size_pinned
+            .iter()
+            .filter(|(w, _)| tiled.iter().any(|window_id| window_id == w))
+            .copied()
+            .collect();
+        let viewport = size_pinned
+            .iter()
+            .fold(self.viewport, |viewport, (_, rect)| {
+                layout::reserve(&viewport, rect)
+            });
+        let unpinned = tiled.filtered(|window_id| !size_pinned.iter().any(|(w, _)| w == window_id));
+
+        if let Some(layout) = self.layouts.focused_mut() {
+            layout.inspect_windows(&self.connection, &unpinned);
+        }
         if let Some(layout) = self.layouts.focused() {
-            layout.layout(&self.connection, &self.viewport, &self.stack)
+            let mut placements = match self.side_pane {
+                Some(ref side_pane) => side_pane.layout(&**layout, &viewport, &unpinned),
+                None => layout.layout(&viewport, &unpinned),
+            };
+            if self.rotated {
+                for (_, placement) in &mut placements {
+                    *placement = layout::rotate(&viewport, placement);
+                }
+            }
+            placements.extend(size_pinned);
+            self.apply_placements(&tiled, &placements);
         }
 
+        self.layout_floating(&stack);
+        self.restack(&stack);
+        self.layout_fullscreen(&stack);
+        self.paint_borders(&stack);
+
         // Tell X to focus the focused window for this group, or to unset
-        // it's focus if we have no windows.
-        match self.stack.focused() {
-            Some(window_id) => self.connection.focus_window(window_id),
+        // it's focus if we have no (visible) windows.
+        match stack.focused() {
+            Some(window_id) => {
+                self.connection.focus_window(window_id);
+                // The window now has the user's attention.
+                self.set_demands_attention(window_id, false);
+            }
             None => self.connection.focus_nothing(),
         }
     }
 
     pub fn add_window(&mut self, window_id: WindowId) {
         info!("Adding window to group {}: {}", self.name(), window_id);
-        self.stack.push(window_id);
+        self.track_mapped_state(&window_id);
+        self.connection.set_wm_state(&window_id, WmState::Normal);
+        match self.split_direction {
+            // Split the focused window, putting the new window next to it
+            // along the split direction's axis.
+            Some(_) => self.stack.insert_after_focused(window_id),
+            None => self.stack.push(window_id),
+        }
+        self.perform_layout();
+    }
+
+    /// Adds a window to the group without focusing it.
+    pub fn add_window_unfocused(&mut self, window_id: WindowId) {
+        info!(
+            "Adding window to group {} without focus: {}",
+            self.name(),
+            window_id
+        );
+        self.track_mapped_state(&window_id);
+        self.connection.set_wm_state(&window_id, WmState::Normal);
+        self.stack.push_unfocused(window_id);
         self.perform_layout();
     }
 
+    /// Forgets any per-window state kept about a window that's leaving the
+    /// group.
+    fn forget_window(&mut self, window_id: &WindowId) {
+        self.hidden.retain(|w| w != window_id);
+        self.mapped.borrow_mut().retain(|w| w != window_id);
+        self.demanding_attention.retain(|w| w != window_id);
+        self.layers.retain(|(w, _)| w != window_id);
+        self.floating.retain(|(w, _)| w != window_id);
+        self.float_geometry.remove(window_id);
+        if self.last_tiled_focus == Some(*window_id) {
+            self.last_tiled_focus = None;
+        }
+        if self.last_floating_focus == Some(*window_id) {
+            self.last_floating_focus = None;
+        }
+        self.size_pinned.retain(|(w, _)| w != window_id);
+        self.fullscreen.retain(|w| w != window_id);
+        if self
+            .side_pane
+            .as_ref()
+            .is_some_and(|pane| pane.window_id() == window_id)
+        {
+            self.side_pane = None;
+        }
+    }
+
     pub fn remove_window(&mut self, window_id: &WindowId) -> WindowId {
         info!("Removing window from group {}: {}", self.name(), window_id);
         let removed = self.stack.remove(|w| w == window_id);
+        self.forget_window(window_id);
         self.perform_layout();
         removed
     }
@@ -111,13 +457,39 @@ impl Group {
             self.stack.focused()
         );
         let removed = self.stack.remove_focused();
+        if let Some(ref window_id) = removed {
+            self.unmap_window(window_id);
+            self.forget_window(window_id);
+        }
         self.perform_layout();
-        removed.map(|window| {
-            self.connection.disable_window_tracking(&window);
-            self.connection.unmap_window(&window);
-            self.connection.enable_window_tracking(&window);
-            window
-        })
+        removed
+    }
+
+    fn is_focused_hidden(&self) -> bool {
+        self.stack
+            .focused()
+            .map(|window_id| self.hidden.contains(window_id))
+            .unwrap_or(false)
+    }
+
+    /// Returns the focused window, if the group has any windows.
+    pub fn focused_window(&self) -> Option<&WindowId> {
+        self.stack.focused()
+    }
+
+    /// Returns the group's windows, in stack order.
+    pub fn windows(&self) -> impl Iterator<Item = &WindowId> {
+        self.stack.iter()
+    }
+
+    /// Returns whether the window has been hidden, e.g. by `hide_window()`.
+    pub fn is_hidden(&self, window_id: &WindowId) -> bool {
+        self.hidden.contains(window_id)
+    }
+
+    /// Returns the name of the group's current layout.
+    pub fn layout_name(&self) -> Option<&str> {
+        self.layouts.focused().map(|layout| layout.name())
     }
 
     pub fn contains(&self, window_id: &WindowId) -> bool {
@@ -136,8 +508,628 @@ impl Group {
         }
     }
 
+    /// Moves focus to the window that's focused once hidden windows are
+    /// skipped over.
+    fn focus_visible(&mut self) {
+        let hidden = &self.hidden;
+        let visible = self.stack.filtered(|window_id| !hidden.contains(window_id));
+        if let Some(window_id) = visible.focused() {
+            self.stack.focus(|w| w == window_id);
+        }
+    }
+
+    /// Hides the focused window, leaving it in the group but unmapped and out
+    /// of the layout. If the focused window is already hidden (which happens
+    /// only when every window is hidden), then it is shown again.
+    pub fn toggle_hide_focused(&mut self) {
+        let window_id = match self.stack.focused() {
+            Some(window_id) => *window_id,
+            None => return,
+        };
+
+        if self.hidden.contains(&window_id) {
+            info!("Showing window in group {}: {}", self.name(), window_id);
+            self.hidden.retain(|w| w != &window_id);
+            self.connection.set_wm_state(&window_id, WmState::Normal);
+        } else {
+            info!("Hiding window in group {}: {}", self.name(), window_id);
+            self.unmap_window(&window_id);
+            self.hidden.push(window_id);
+            self.connection.set_wm_state(&window_id, WmState::Iconic);
+            self.focus_visible();
+        }
+        self.perform_layout();
+    }
+
+    /// Records whether a window joining the group is already mapped (e.g. if
+    /// it was mapped before we started).
+    fn track_mapped_state(&self, window_id: &WindowId) {
+        if self.connection.is_window_viewable(window_id) {
+            self.mapped.borrow_mut().push(*window_id);
+        }
+    }
+
+    /// Maps a window, unless it's already mapped.
+    ///
+    /// Callers should disable window tracking around this, as they'll usually
+    /// configure the window at the same time.
+    fn map_window(&self, window_id: &WindowId) {
+        if self.mapped.borrow().contains(window_id) {
+            return;
+        }
+        self.connection.map_window(window_id);
+        self.mapped.borrow_mut().push(*window_id);
+    }
+
+    /// Unmaps a window, unless it's already unmapped, without treating it as
+    /// the window having gone.
+    fn unmap_window(&self, window_id: &WindowId) {
+        if !self.mapped.borrow().contains(window_id) {
+            return;
+        }
+        self.connection.disable_window_tracking(window_id);
+        self.connection.unmap_window(window_id);
+        self.connection.enable_window_tracking(window_id);
+        self.mapped.borrow_mut().retain(|w| w != window_id);
+    }
+
+    /// Maps and moves each window to its placement, and unmaps any windows in
+    /// `stack` which weren't given one.
+    fn apply_placements(&self, stack: &Stack<WindowId>, placements: &[(WindowId, Viewport)]) {
+        for window_id in stack.iter() {
+            if placements.iter().any(|(w, _)| w == window_id) {
+                continue;
+            }
+            self.unmap_window(window_id);
+        }
+
+        for (window_id, placement) in placements {
+            self.connection.disable_window_tracking(window_id);
+            self.map_window(window_id);
+            self.connection.configure_window(
+                window_id,
+                placement.x,
+                placement.y,
+                placement.width,
+                placement.height,
+            );
+            self.connection.enable_window_tracking(window_id);
+        }
+    }
+
+    /// Maps the (visible) floating windows at their geometry, above the tiled
+    /// windows.
+    fn layout_floating(&self, stack: &Stack<WindowId>) {
+        for (window_id, rect) in &self.floating {
+            if !stack.iter().any(|w| w == window_id) {
+                continue;
+            }
+            self.connection.disable_window_tracking(window_id);
+            self.map_window(window_id);
+            self.connection.configure_window_exact(
+                window_id,
+                rect.x,
+                rect.y,
+                rect.width,
+                rect.height,
+            );
+            self.connection.enable_window_tracking(window_id);
+            self.connection.restack_window(window_id, true);
+        }
+    }
+
+    /// Maps the (visible) fullscreen windows over the whole screen, above
+    /// everything else.
+    fn layout_fullscreen(&self, stack: &Stack<WindowId>) {
+        if self.fullscreen.is_empty() {
+            return;
+        }
+        let root = self.connection.root_window_id();
+        let (x, y, width, height) = match self.connection.get_window_rect(root) {
+            Some(rect) => rect,
+            None => return,
+        };
+        for window_id in &self.fullscreen {
+            if !stack.iter().any(|w| w == window_id) {
+                continue;
+            }
+            self.connection.disable_window_tracking(window_id);
+            self.map_window(window_id);
+            self.connection
+                .configure_window_exact(window_id, x, y, width, height);
+            self.connection.enable_window_tracking(window_id);
+            self.connection.restack_window(window_id, true);
+        }
+    }
+
+    pub fn is_fullscreen(&self, window_id: &WindowId) -> bool {
+        self.fullscreen.contains(window_id)
+    }
+
+    /// Makes the window cover the whole screen, or returns it to where it was,
+    /// keeping its _NET_WM_STATE up to date.
+    pub fn set_fullscreen(&mut self, window_id: &WindowId, fullscreen: bool) {
+        if fullscreen == self.is_fullscreen(window_id) {
+            return;
+        }
+        info!(
+            "Setting window in group {} fullscreen: {}: {}",
+            self.name, window_id, fullscreen
+        );
+        self.fullscreen.retain(|w| w != window_id);
+        if fullscreen {
+            self.fullscreen.push(*window_id);
+            self.connection.set_window_border_width(window_id, 0);
+        } else {
+            self.connection
+                .set_window_border_width(window_id, self.borders.width);
+        }
+        self.connection
+            .set_window_state(window_id, WindowState::Fullscreen, fullscreen);
+        self.perform_layout();
+    }
+
+    pub fn is_floating(&self, window_id: &WindowId) -> bool {
+        self.floating.iter().any(|(w, _)| w == window_id)
+    }
+
+    /// Makes the window float at the given geometry.
+    pub fn set_floating(&mut self, window_id: &WindowId, rect: Viewport) {
+        info!("Floating window in group {}: {}", self.name, window_id);
+        self.floating.retain(|(w, _)| w != window_id);
+        self.floating.push((*window_id, rect));
+        self.float_geometry.insert(*window_id, rect);
+        self.perform_layout();
+    }
+
+    /// Returns the geometry of a floating window, or `None` if the window
+    /// isn't floating.
+    pub fn floating_geometry(&self, window_id: &WindowId) -> Option<Viewport> {
+        self.floating
+            .iter()
+            .find(|(w, _)| w == window_id)
+            .map(|(_, rect)| *rect)
+    }
+
+    /// Returns the geometry of each of the group's floating windows.
+    pub fn floating_geometries(&self) -> impl Iterator<Item = Viewport> + '_ {
+        self.floating.iter().map(|(_, rect)| *rect)
+    }
+
+    /// Moves (or resizes) a floating window, without laying out the other
+    /// windows, e.g. while it's being dragged.
+    pub fn move_floating(&mut self, window_id: &WindowId, rect: Viewport) {
+        if let Some((_, current)) = self.floating.iter_mut().find(|(w, _)| w == window_id) {
+            *current = rect;
+            self.float_geometry.insert(*window_id, rect);
+            self.connection.configure_window_exact(
+                window_id,
+                rect.x,
+                rect.y,
+                rect.width,
+                rect.height,
+            );
+        }
+    }
+
+    /// Makes the focused window float, or returns it to the layout if it is
+    /// already floating.
+    ///
+    /// A window which has floated before returns to the geometry it last had
+    /// while floating. Otherwise, it floats at its current geometry.
+    pub fn toggle_float_focused(&mut self) {
+        let window_id = match self.stack.focused() {
+            Some(window_id) => *window_id,
+            None => return,
+        };
+        if self.is_floating(&window_id) {
+            info!("Tiling window in group {}: {}", self.name, window_id);
+            self.floating.retain(|(w, _)| *w != window_id);
+            self.perform_layout();
+        } else if let Some(rect) = self.float_geometry.get(&window_id).copied() {
+            self.set_floating(&window_id, rect);
+        } else if let Some((x, y, width, height)) = self.connection.get_window_rect(&window_id) {
+            let rect = Viewport {
+                x,
+                y,
+                width,
+                height,
+                monitor: self.viewport.monitor,
+            };
+            self.set_floating(&window_id, rect);
+        }
+    }
+
+    /// Moves the focus between the tiled windows and the floating windows,
+    /// returning to the window that was focused when the layer was last left.
+    ///
+    /// If that window has since gone (or changed layer), the topmost floating
+    /// window or the first tiled window is focused instead.
+    pub fn toggle_focus_layer(&mut self) {
+        let focused = match self.stack.focused() {
+            Some(window_id) => *window_id,
+            None => return,
+        };
+        let target = if self.is_floating(&focused) {
+            self.last_floating_focus = Some(focused);
+            self.last_tiled_focus
+                .filter(|w| self.contains(w) && !self.is_floating(w))
+                .or_else(|| {
+                    self.stack
+                        .iter()
+                        .find(|w| !self.is_floating(w) && !self.hidden.contains(w))
+                        .copied()
+                })
+        } else {
+            self.last_tiled_focus = Some(focused);
+            self.last_floating_focus
+                .filter(|w| self.is_floating(w))
+                .or_else(|| {
+                    // Floating windows are raised in order, so the last is on top.
+                    self.floating
+                        .iter()
+                        .rev()
+                        .map(|(w, _)| *w)
+                        .find(|w| !self.hidden.contains(w))
+                })
+        };
+        if let Some(window_id) = target {
+            info!(
+                "Switching focus layer in group {}: {} to {}",
+                self.name, focused, window_id
+            );
+            self.focus(&window_id);
+        }
+    }
+
+    /// Returns every floating window to the layout, laying out once at the
+    /// end.
+    ///
+    /// Their float geometry is remembered, as with `toggle_float_focused()`.
+    pub fn tile_all_floating(&mut self) {
+        info!(
+            "Tiling {} floating windows in group {}",
+            self.floating.len(),
+            self.name
+        );
+        self.floating.clear();
+        self.perform_layout();
+    }
+
+    /// Pins the focused window's size, so that it keeps its current geometry
+    /// while the layout tiles the other windows around it. If its size is
+    /// already pinned, it's returned to the layout.
+    pub fn toggle_pin_size_focused(&mut self) {
+        let window_id = match self.stack.focused() {
+            Some(window_id) => *window_id,
+            None => return,
+        };
+        if self.size_pinned.iter().any(|(w, _)| *w == window_id) {
+            info!(
+                "Unpinning size of window in group {}: {}",
+                self.name, window_id
+            );
+            self.size_pinned.retain(|(w, _)| *w != window_id);
+        } else if let Some((x, y, width, height)) =
+            self.connection.get_window_outer_rect(&window_id)
+        {
+            // Keep the border, as the layout's placements include it.
+            info!(
+                "Pinning size of window in group {}: {}",
+                self.name, window_id
+            );
+            let rect = Viewport {
+                x,
+                y,
+                width,
+                height,
+                monitor: self.viewport.monitor,
+            };
+            self.size_pinned.push((window_id, rect));
+        }
+        self.perform_layout();
+    }
+
+    /// Records the current geometry of a floating window, e.g. after its
+    /// client has moved or resized it.
+    pub fn update_floating_geometry(&mut self, window_id: &WindowId) {
+        if !self.is_floating(window_id) {
+            return;
+        }
+        if let Some((x, y, width, height)) = self.connection.get_window_rect(window_id) {
+            let rect = Viewport {
+                x,
+                y,
+                width,
+                height,
+                monitor: self.viewport.monitor,
+            };
+            if let Some((_, current)) = self.floating.iter_mut().find(|(w, _)| w == window_id) {
+                *current = rect;
+            }
+            self.float_geometry.insert(*window_id, rect);
+        }
+    }
+
+    fn paint_borders(&self, stack: &Stack<WindowId>) {
+        if self.borders.width == 0 {
+            return;
+        }
+        let focused = stack.focused();
+        for window_id in stack.iter() {
+            let color = if Some(window_id) == focused {
+                self.borders.focused_color
+            } else {
+                self.borders.unfocused_color
+            };
+            self.connection.set_window_border_color(window_id, color);
+        }
+    }
+
+    /// Puts the (visible) windows in the stacking order given by their layers,
+    /// with the docks above them.
+    fn restack(&self, stack: &Stack<WindowId>) {
+        for (window_id, layer) in &self.layers {
+            if !stack.iter().any(|w| w == window_id) {
+                continue;
+            }
+            match layer {
+                Layer::Below => self.connection.restack_window(window_id, false),
+                Layer::Normal => {}
+                Layer::Above => self.connection.restack_window(window_id, true),
+            }
+        }
+        // Fullscreen windows are raised above the docks afterwards.
+        for dock in &self.docks {
+            self.connection.restack_window(dock, true);
+        }
+    }
+
+    /// Sets the docks to keep above the group's windows.
+    pub(crate) fn set_docks(&mut self, docks: Vec<WindowId>) {
+        self.docks = docks;
+    }
+
+    /// Raises the docks back above a window which has just been raised, and
+    /// then any fullscreen windows above them.
+    pub(crate) fn raise_docks(&self) {
+        for window_id in self.docks.iter().chain(&self.fullscreen) {
+            self.connection.restack_window(window_id, true);
+        }
+    }
+
+    pub fn layer(&self, window_id: &WindowId) -> Layer {
+        self.layers
+            .iter()
+            .find(|(w, _)| w == window_id)
+            .map_or(Layer::Normal, |(_, layer)| *layer)
+    }
+
+    /// Moves the window to a stacking layer, keeping its _NET_WM_STATE up to
+    /// date.
+    pub fn set_layer(&mut self, window_id: &WindowId, layer: Layer) {
+        let previous = self.layer(window_id);
+        if previous == layer {
+            return;
+        }
+        info!(
+            "Moving window in group {} to layer: {}: {:?}",
+            self.name, window_id, layer
+        );
+        self.layers.retain(|(w, _)| w != window_id);
+        if layer != Layer::Normal {
+            self.layers.push((*window_id, layer));
+        }
+        if let Some(state) = previous.window_state() {
+            self.connection.set_window_state(window_id, state, false);
+        }
+        if let Some(state) = layer.window_state() {
+            self.connection.set_window_state(window_id, state, true);
+        }
+        self.perform_layout();
+    }
+
+    /// Moves the focused window into `layer`, or back to `Layer::Normal` if
+    /// it is already in it.
+    pub fn toggle_focused_layer(&mut self, layer: Layer) {
+        if let Some(window_id) = self.stack.focused().copied() {
+            let layer = if self.layer(&window_id) == layer {
+                Layer::Normal
+            } else {
+                layer
+            };
+            self.set_layer(&window_id, layer);
+        }
+    }
+
+    /// Returns whether the window is demanding the user's attention.
+    pub fn demands_attention(&self, window_id: &WindowId) -> bool {
+        self.demanding_attention.contains(window_id)
+    }
+
+    /// Marks whether the window is demanding the user's attention, keeping its
+    /// _NET_WM_STATE up to date.
+    ///
+    /// The focused window of the active group never demands attention, as it
+    /// already has it.
+    pub fn set_demands_attention(&mut self, window_id: &WindowId, demands: bool) {
+        let focused = self.active && self.stack.focused() == Some(window_id);
+        let demands = demands && !focused;
+        if demands == self.demands_attention(window_id) {
+            return;
+        }
+        info!(
+            "Window in group {} demands attention: {}: {}",
+            self.name, window_id, demands
+        );
+        self.demanding_attention.retain(|w| w != window_id);
+        if demands {
+            self.demanding_attention.push(*window_id);
+        }
+        self.connection
+            .set_window_state(window_id, WindowState::DemandsAttention, demands);
+    }
+
+    /// Pins the focused window to a pane taking up `ratio` of the viewport's
+    /// width, with the rest of the windows laid out beside it. If a window is
+    /// already pinned, it is released back into the layout.
+    pub fn toggle_side_pane(&mut self, ratio: f32) {
+        if self.side_pane.take().is_none() {
+            if let Some(window_id) = self.stack.focused() {
+                info!("Pinning window in group {}: {}", self.name(), window_id);
+                self.side_pane = Some(SidePane::new(*window_id, ratio));
+            }
+        }
+        self.perform_layout();
+    }
+
+    /// Returns whether the group's windows are unmapped to show the desktop.
+    pub fn is_showing_desktop(&self) -> bool {
+        self.showing_desktop
+    }
+
+    /// Shows the desktop by unmapping all of the group's windows (while
+    /// leaving them in the group), or restores the windows.
+    pub fn set_showing_desktop(&mut self, showing: bool) {
+        info!("Showing desktop in group {}: {}", self.name(), showing);
+        self.showing_desktop = showing;
+        if showing {
+            for window_id in self.stack.iter() {
+                self.unmap_window(window_id);
+            }
+        }
+        self.perform_layout();
+    }
+
+    /// Hides a window in the group, leaving it unmapped and out of the layout
+    /// until it's shown again (e.g. by `unhide_all()`).
+    pub fn hide_window(&mut self, window_id: &WindowId) {
+        if !self.contains(window_id) || self.hidden.contains(window_id) {
+            return;
+        }
+        info!("Hiding window in group {}: {}", self.name(), window_id);
+        self.unmap_window(window_id);
+        self.hidden.push(*window_id);
+        self.connection.set_wm_state(window_id, WmState::Iconic);
+        self.focus_visible();
+        self.perform_layout();
+    }
+
+    /// Shows all hidden windows in the group.
+    pub fn unhide_all(&mut self) {
+        info!("Showing all hidden windows in group {}", self.name());
+        for window_id in self.hidden.drain(..) {
+            self.connection.set_wm_state(&window_id, WmState::Normal);
+        }
+        self.perform_layout();
+    }
+
+    /// Focuses the nearest visible window in the given direction, by where the
+    /// windows are on the screen.
+    pub fn focus_direction(&mut self, direction: Direction) {
+        let focused = match self.stack.focused() {
+            Some(focused) => *focused,
+            None => return,
+        };
+        let rect = |window_id: &WindowId| {
+            self.connection
+                .get_window_rect(window_id)
+                .map(|(x, y, width, height)| Viewport {
+                    x,
+                    y,
+                    width,
+                    height,
+                    monitor: self.viewport.monitor,
+                })
+        };
+        let from = match rect(&focused) {
+            Some(from) => from,
+            None => return,
+        };
+        let (window_ids, rects): (Vec<WindowId>, Vec<Viewport>) = self
+            .stack
+            .iter()
+            .filter(|window_id| {
+                **window_id != focused
+                    && !self.hidden.contains(window_id)
+                    && self.connection.is_window_viewable(window_id)
+            })
+            .filter_map(|window_id| Some((*window_id, rect(window_id)?)))
+            .unzip();
+        if let Some(i) = direction.nearest(&from, &rects) {
+            info!(
+                "Focusing window {:?} of {} in group {}",
+                direction, focused, self.name
+            );
+            self.focus(&window_ids[i]);
+        }
+    }
+
+    /// Focuses the visible window taking up the most space on the screen.
+    pub fn focus_largest(&mut self) {
+        self.focus_by_area(Reverse);
+    }
+
+    /// Focuses the visible window taking up the least space on the screen,
+    /// e.g. to find a stray dialog.
+    pub fn focus_smallest(&mut self) {
+        self.focus_by_area(|area| area);
+    }
+
+    /// Focuses the visible window whose on-screen area gives the lowest `key`,
+    /// preferring windows earlier in the stack in a tie.
+    fn focus_by_area<F, K>(&mut self, key: F)
+    where
+        F: Fn(u64) -> K,
+        K: Ord,
+    {
+        let window_id = self
+            .stack
+            .iter()
+            .filter(|window_id| {
+                !self.hidden.contains(window_id) && self.connection.is_window_viewable(window_id)
+            })
+            .filter_map(|window_id| {
+                let (_, _, width, height) = self.connection.get_window_rect(window_id)?;
+                Some((*window_id, u64::from(width) * u64::from(height)))
+            })
+            .min_by_key(|&(_, area)| key(area))
+            .map(|(window_id, _)| window_id);
+        if let Some(window_id) = window_id {
+            info!(
+                "Focusing window {} in group {} by area",
+                window_id, self.name
+            );
+            self.focus(&window_id);
+        }
+    }
+
+    /// Focuses the window at `index` in the stack, starting at 0. Does
+    /// nothing if there's no such window, or if it's hidden.
+    pub fn focus_index(&mut self, index: usize) {
+        match self.stack.iter().nth(index) {
+            Some(window_id) if !self.hidden.contains(window_id) => {}
+            _ => return,
+        }
+        self.stack.focus_index(index);
+        info!(
+            "Focusing window {} in group {}: {:?}",
+            index,
+            self.name(),
+            self.stack.focused()
+        );
+        self.perform_layout();
+    }
+
     pub fn focus_next(&mut self) {
-        self.stack.focus_next();
+        // Skip over hidden windows, giving up if we come back round to where
+        // we started.
+        for _ in 0..self.stack.len() {
+            self.stack.focus_next();
+            if !self.is_focused_hidden() {
+                break;
+            }
+        }
         info!(
             "Focusing next window in group {}: {:?}",
             self.name(),
@@ -147,7 +1139,12 @@ impl Group {
     }
 
     pub fn focus_previous(&mut self) {
-        self.stack.focus_previous();
+        for _ in 0..self.stack.len() {
+            self.stack.focus_previous();
+            if !self.is_focused_hidden() {
+                break;
+            }
+        }
         info!(
             "Focusing previous window in group {}: {:?}",
             self.name(),
@@ -186,14 +1183,195 @@ impl Group {
         self.perform_layout();
     }
 
+    pub fn move_up(&mut self) {
+        info!(
+            "Moving focused window up in group {}: {:?}",
+            self.name(),
+            self.stack.focused()
+        );
+        self.stack.move_previous();
+        self.perform_layout();
+    }
+
+    pub fn move_down(&mut self) {
+        info!(
+            "Moving focused window down in group {}: {:?}",
+            self.name(),
+            self.stack.focused()
+        );
+        self.stack.move_next();
+        self.perform_layout();
+    }
+
+    /// Swaps the focused window with the first window in the stack (the
+    /// master window, in master/stack layouts).
+    pub fn swap_master(&mut self) {
+        info!(
+            "Swapping focused window with master in group {}: {:?}",
+            self.name(),
+            self.stack.focused()
+        );
+        self.stack.swap_focused_with_front();
+        self.perform_layout();
+    }
+
+    pub fn move_to_front(&mut self) {
+        info!(
+            "Moving focused window to front of group {}: {:?}",
+            self.name(),
+            self.stack.focused()
+        );
+        self.stack.move_focused_to_front();
+        self.perform_layout();
+    }
+
+    pub fn move_to_back(&mut self) {
+        info!(
+            "Moving focused window to back of group {}: {:?}",
+            self.name(),
+            self.stack.focused()
+        );
+        self.stack.move_focused_to_back();
+        self.perform_layout();
+    }
+
+    /// Switches to the named layout, returning `false` if the group doesn't
+    /// have a layout with that name.
+    pub fn set_layout(&mut self, name: &str) -> bool {
+        if !self.layouts.iter().any(|layout| layout.name() == name) {
+            return false;
+        }
+        self.layouts.focus(|layout| layout.name() == name);
+        info!("Switching to layout in group {}: {}", self.name, name);
+        self.perform_layout();
+        true
+    }
+
+    /// Switches to the preset's layout and applies its parameters, returning
+    /// `false` if the group doesn't have the preset's layout.
+    pub fn apply_preset(&mut self, preset: &LayoutPreset) -> bool {
+        if !self
+            .layouts
+            .iter()
+            .any(|layout| layout.name() == preset.layout())
+        {
+            return false;
+        }
+        info!(
+            "Applying layout preset in group {}: {:?}",
+            self.name, preset
+        );
+        self.layouts
+            .focus(|layout| layout.name() == preset.layout());
+        if let Some(layout) = self.layouts.focused_mut() {
+            preset.configure(&mut **layout);
+        }
+        self.preset = Some(preset.name().to_owned());
+        self.perform_layout();
+        true
+    }
+
+    /// Returns the name of the preset last applied to the group, if any.
+    pub fn preset_name(&self) -> Option<&str> {
+        self.preset.as_deref()
+    }
+
+    /// Switches back to the layout the group was built with.
+    pub fn reset_layout(&mut self) {
+        let default_layout = self.default_layout.clone();
+        if !self.set_layout(&default_layout) {
+            warn!(
+                "Default layout of group {} doesn't exist: {}",
+                self.name, default_layout
+            );
+        }
+    }
+
+    /// Toggles whether new windows split the focused window vertically or
+    /// horizontally.
+    ///
+    /// Until this is first called, new windows are added to the end of the
+    /// stack and the layout's orientation is left alone. Afterwards, they're
+    /// inserted after the focused window. Each toggle gives the layout the
+    /// split direction's orientation once, so `toggle_layout_orientation()`
+    /// still works in between. The first toggle splits horizontally.
+    pub fn toggle_split_direction(&mut self) {
+        let direction = match self.split_direction {
+            Some(direction) => direction.flipped(),
+            None => Orientation::Horizontal,
+        };
+        info!(
+            "Setting split direction of group {}: {:?}",
+            self.name, direction
+        );
+        self.split_direction = Some(direction);
+        if let Some(layout) = self.layouts.focused_mut() {
+            layout.set_orientation(direction);
+        }
+        self.perform_layout();
+    }
+
+    pub fn toggle_layout_orientation(&mut self) {
+        if let Some(layout) = self.layouts.focused_mut() {
+            info!(
+                "Toggling orientation of layout in group {}: {:?}",
+                self.name, layout
+            );
+            layout.toggle_orientation();
+        }
+        self.perform_layout();
+    }
+
+    /// Rotates the arrangement of the group's layout by 90°, or back again.
+    pub fn toggle_layout_rotation(&mut self) {
+        self.rotated = !self.rotated;
+        info!(
+            "Setting layout rotation of group {}: {}",
+            self.name, self.rotated
+        );
+        self.perform_layout();
+    }
+
+    pub fn toggle_layout_master_side(&mut self) {
+        if let Some(layout) = self.layouts.focused_mut() {
+            info!(
+                "Toggling master side of layout in group {}: {:?}",
+                self.name, layout
+            );
+            layout.toggle_master_side();
+        }
+        self.perform_layout();
+    }
+
+    pub fn adjust_layout_master_ratio(&mut self, delta: f32) {
+        if let Some(layout) = self.layouts.focused_mut() {
+            info!(
+                "Adjusting master ratio of layout in group {} by {}: {:?}",
+                self.name, delta, layout
+            );
+            layout.adjust_master_ratio(delta);
+        }
+        self.perform_layout();
+    }
+
+    pub fn adjust_layout_gap(&mut self, delta: i32) {
+        if let Some(layout) = self.layouts.focused_mut() {
+            info!(
+                "Adjusting gap of layout in group {} by {}: {:?}",
+                self.name, delta, layout
+            );
+            layout.adjust_gap(delta);
+        }
+        self.perform_layout();
+    }
+
     pub fn layout_previous(&mut self) {
-        self.layouts.focus_next();
+        self.layouts.focus_previous();
         info!(
             "Switching to previous layout in group {}: {:?}",
             self.name(),
             self.layouts.focused()
         );
-        self.layouts.focus_previous();
         self.perform_layout();
     }
 }
@@ -31,6 +31,20 @@ impl ModKey {
             | xcb::MOD_MASK_5
     }
 
+    /// Returns the combinations of the lock modifiers (CapsLock, and NumLock
+    /// which is usually Mod2), which key bindings should fire regardless of.
+    ///
+    /// This means bindings which use `Lock` or `Mod2` never match.
+    pub fn lock_combinations() -> [ModMask; 4] {
+        let num_lock = xcb::MOD_MASK_2;
+        [
+            0,
+            xcb::MOD_MASK_LOCK,
+            num_lock,
+            xcb::MOD_MASK_LOCK | num_lock,
+        ]
+    }
+
     fn mask(self) -> ModMask {
         match self {
             ModKey::Shift => xcb::MOD_MASK_SHIFT,
@@ -45,6 +59,16 @@ impl ModKey {
     }
 }
 
+/// Whether a key binding fires when its key is pressed or released.
+///
+/// Firing on release is useful for keys (such as some media keys) which
+/// auto-repeat, as the binding will only fire once the key is let go.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum KeyTrigger {
+    Press,
+    Release,
+}
+
 /// A single key, of the same type as the `x11::keysym` constants.
 type Key = c_uint;
 
@@ -57,13 +81,37 @@ pub struct KeyCombo {
 
 impl KeyCombo {
     fn new(mods: &[ModKey], keysym: Key) -> KeyCombo {
-        let mod_mask = mods.iter().fold(0, |mask, mod_key| mask | mod_key.mask());
-        KeyCombo { mod_mask, keysym }
+        KeyCombo {
+            mod_mask: mod_mask(mods),
+            keysym,
+        }
     }
+
+    /// Returns the combo for a key event with the given modifier `state`,
+    /// leaving out any lock modifiers and mouse buttons.
+    pub fn from_event(state: u16, keysym: Key) -> KeyCombo {
+        KeyCombo {
+            mod_mask: event_mod_mask(state),
+            keysym,
+        }
+    }
+}
+
+fn mod_mask(mods: &[ModKey]) -> ModMask {
+    mods.iter().fold(0, |mask, mod_key| mask | mod_key.mask())
+}
+
+/// Returns the modifiers held in an input event's `state`, leaving out any
+/// lock modifiers and mouse buttons.
+fn event_mod_mask(state: u16) -> ModMask {
+    let locks = ModKey::lock_combinations()
+        .iter()
+        .fold(0, |mask, locks| mask | locks);
+    u32::from(state) & ModKey::mask_all() & !locks
 }
 
 pub struct KeyHandlers {
-    hashmap: HashMap<KeyCombo, Command>,
+    hashmap: HashMap<KeyCombo, (Command, KeyTrigger)>,
 }
 
 impl KeyHandlers {
@@ -71,17 +119,117 @@ impl KeyHandlers {
         self.hashmap.keys().collect()
     }
 
-    pub fn get(&self, key_combo: &KeyCombo) -> Option<Command> {
-        self.hashmap.get(key_combo).cloned()
+    /// Returns the handler for the key combo, if it should fire on `trigger`.
+    pub fn get(&self, key_combo: &KeyCombo, trigger: KeyTrigger) -> Option<Command> {
+        self.hashmap
+            .get(key_combo)
+            .filter(|(_, t)| *t == trigger)
+            .map(|(handler, _)| handler.clone())
     }
 }
 
-impl From<Vec<(Vec<ModKey>, Key, Command)>> for KeyHandlers {
-    fn from(handlers: Vec<(Vec<ModKey>, Key, Command)>) -> KeyHandlers {
+impl From<Vec<(Vec<ModKey>, Key, Command, KeyTrigger)>> for KeyHandlers {
+    fn from(handlers: Vec<(Vec<ModKey>, Key, Command, KeyTrigger)>) -> KeyHandlers {
         let mut hashmap = HashMap::new();
-        for (modkeys, keysym, handler) in handlers {
-            hashmap.insert(KeyCombo::new(&modkeys, keysym), handler);
+        for (modkeys, keysym, handler, trigger) in handlers {
+            hashmap.insert(KeyCombo::new(&modkeys, keysym), (handler, trigger));
         }
         KeyHandlers { hashmap }
     }
 }
+
+impl From<Vec<(Vec<ModKey>, Key, Command)>> for KeyHandlers {
+    fn from(handlers: Vec<(Vec<ModKey>, Key, Command)>) -> KeyHandlers {
+        handlers
+            .into_iter()
+            .map(|(modkeys, keysym, handler)| (modkeys, keysym, handler, KeyTrigger::Press))
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+/// A mouse button, numbered as X numbers them: 1 is usually the left button
+/// and 3 the right button.
+pub type Button = u8;
+
+/// What dragging the pointer with a mouse binding held does to the window
+/// it was over.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MouseAction {
+    /// Moves the window, making it float if it's tiled.
+    Move,
+    /// Resizes the window from its bottom right corner, making it float if
+    /// it's tiled.
+    Resize,
+}
+
+/// A combination of zero or more mods and a mouse button.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ButtonCombo {
+    pub mod_mask: ModMask,
+    pub button: Button,
+}
+
+impl ButtonCombo {
+    fn new(mods: &[ModKey], button: Button) -> ButtonCombo {
+        ButtonCombo {
+            mod_mask: mod_mask(mods),
+            button,
+        }
+    }
+
+    /// Returns the combo for a button event with the given modifier `state`,
+    /// leaving out any lock modifiers and other mouse buttons.
+    pub fn from_event(state: u16, button: Button) -> ButtonCombo {
+        ButtonCombo {
+            mod_mask: event_mod_mask(state),
+            button,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct MouseHandlers {
+    hashmap: HashMap<ButtonCombo, MouseAction>,
+}
+
+impl MouseHandlers {
+    pub fn button_combos(&self) -> Vec<&ButtonCombo> {
+        self.hashmap.keys().collect()
+    }
+
+    pub fn get(&self, button_combo: &ButtonCombo) -> Option<MouseAction> {
+        self.hashmap.get(button_combo).copied()
+    }
+}
+
+impl From<Vec<(Vec<ModKey>, Button, MouseAction)>> for MouseHandlers {
+    fn from(handlers: Vec<(Vec<ModKey>, Button, MouseAction)>) -> MouseHandlers {
+        let hashmap = handlers
+            .into_iter()
+            .map(|(modkeys, button, action)| (ButtonCombo::new(&modkeys, button), action))
+            .collect();
+        MouseHandlers { hashmap }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_key_combo_from_event_ignores_locks() {
+        let state =
+            xcb::MOD_MASK_4 | xcb::MOD_MASK_LOCK | xcb::MOD_MASK_2 | xcb::KEY_BUT_MASK_BUTTON_1;
+        let combo = KeyCombo::from_event(state as u16, 106);
+        assert_eq!(combo, KeyCombo::new(&[ModKey::Mod4], 106));
+    }
+
+    #[test]
+    fn test_mouse_handlers_ignore_other_buttons_held() {
+        let handlers: MouseHandlers = vec![(vec![ModKey::Mod4], 1, MouseAction::Move)].into();
+        let state = xcb::MOD_MASK_4 | xcb::MOD_MASK_2 | xcb::BUTTON_MASK_3;
+        let combo = ButtonCombo::from_event(state as u16, 1);
+        assert_eq!(handlers.get(&combo), Some(MouseAction::Move));
+    }
+}
@@ -0,0 +1,98 @@
+use crate::layout::tiled::tiles;
+use crate::layout::{adjusted_gap, placements, Layout, Orientation};
+use crate::stack::Stack;
+use crate::x::WindowId;
+use crate::Viewport;
+
+/// Tiles windows side-by-side, each taking an equal share of the width and the
+/// full height.
+#[derive(Clone)]
+pub struct ColumnsLayout {
+    name: String,
+    padding: u32,
+}
+
+impl ColumnsLayout {
+    pub fn new<S: Into<String>>(name: S, padding: u32) -> ColumnsLayout {
+        ColumnsLayout {
+            name: name.into(),
+            padding,
+        }
+    }
+}
+
+impl Layout for ColumnsLayout {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn adjust_gap(&mut self, delta: i32) {
+        self.padding = adjusted_gap(self.padding, delta);
+    }
+
+    fn set_gap(&mut self, gap: u32) {
+        self.padding = gap;
+    }
+
+    fn layout(&self, viewport: &Viewport, stack: &Stack<WindowId>) -> Vec<(WindowId, Viewport)> {
+        let geometry = tiles(viewport, self.padding, Orientation::Horizontal, stack.len());
+        // Windows which don't fit in a tiny viewport are left unmapped.
+        placements(viewport, stack, geometry)
+            .into_iter()
+            .filter(|(_, placement)| placement.width > 0 && placement.height > 0)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const VIEWPORT: Viewport = Viewport {
+        x: 100,
+        y: 0,
+        width: 1000,
+        height: 600,
+        monitor: 0,
+    };
+
+    fn columns(count: u32) -> Vec<(u32, u32, u32, u32)> {
+        let stack = Stack::from((1..=count).map(WindowId::from).collect::<Vec<_>>());
+        ColumnsLayout::new("columns", 10)
+            .layout(&VIEWPORT, &stack)
+            .into_iter()
+            .map(|(_, p)| (p.x, p.y, p.width, p.height))
+            .collect()
+    }
+
+    #[test]
+    fn test_columns_one() {
+        assert_eq!(columns(1), vec![(110, 10, 980, 580)]);
+    }
+
+    #[test]
+    fn test_columns_x_offsets() {
+        let geometry = columns(3);
+        assert_eq!(
+            geometry.iter().map(|&(x, _, _, _)| x).collect::<Vec<_>>(),
+            vec![110, 440, 770]
+        );
+        for pair in geometry.windows(2) {
+            let (x, _, width, _) = pair[0];
+            assert_eq!(pair[1].0, x + width + 10);
+        }
+    }
+
+    #[test]
+    fn test_columns_full_height() {
+        for &(_, y, _, height) in &columns(3) {
+            assert_eq!((y, height), (10, 580));
+        }
+    }
+
+    #[test]
+    fn test_columns_skips_windows_which_dont_fit() {
+        // With 100 windows, each column would be less than the padding wide.
+        assert!(columns(100).is_empty());
+    }
+}
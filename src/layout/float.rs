@@ -0,0 +1,149 @@
+use std::cmp;
+
+use crate::Viewport;
+
+/// Where windows which float without asking for a position are placed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FloatPlacement {
+    /// In the center of the screen. This is the default.
+    Center,
+    /// Centered under the pointer, but kept on the screen.
+    Mouse,
+    /// At the first spot, scanning from the top left, where the window doesn't
+    /// overlap any other floating window. Falls back to the center of the
+    /// screen if there's no such spot.
+    Smart,
+}
+
+impl FloatPlacement {
+    /// Returns the `(x, y)` at which to place a floating window of the given
+    /// size, given the position of the pointer and the geometry of the other
+    /// floating windows.
+    pub(crate) fn place(
+        self,
+        viewport: &Viewport,
+        (width, height): (u32, u32),
+        pointer: Option<(i16, i16)>,
+        floats: &[Viewport],
+    ) -> (u32, u32) {
+        let center = (
+            viewport.x + viewport.width.saturating_sub(width) / 2,
+            viewport.y + viewport.height.saturating_sub(height) / 2,
+        );
+        match self {
+            FloatPlacement::Center => center,
+            FloatPlacement::Mouse => match pointer {
+                Some((x, y)) => (
+                    clamp_start(
+                        i64::from(x) - i64::from(width / 2),
+                        width,
+                        viewport.x,
+                        viewport.width,
+                    ),
+                    clamp_start(
+                        i64::from(y) - i64::from(height / 2),
+                        height,
+                        viewport.y,
+                        viewport.height,
+                    ),
+                ),
+                None => center,
+            },
+            FloatPlacement::Smart => free_spot(viewport, (width, height), floats).unwrap_or(center),
+        }
+    }
+}
+
+/// Moves a window's `start` so that as much of its `size` as possible lies
+/// within the `length` pixels from `min`.
+fn clamp_start(start: i64, size: u32, min: u32, length: u32) -> u32 {
+    let max = i64::from(min) + i64::from(length.saturating_sub(size));
+    cmp::max(cmp::min(start, max), i64::from(min)) as u32
+}
+
+/// Finds the first spot (scanning rows from the top, and then each row from
+/// the left) where a window of the given size fits within the viewport
+/// without overlapping any of `floats`.
+///
+/// Only spots at the viewport's edges or against the bottom or right edge of
+/// another floating window are tried, as those are the only places the
+/// window can be pushed up or left against.
+fn free_spot(
+    viewport: &Viewport,
+    (width, height): (u32, u32),
+    floats: &[Viewport],
+) -> Option<(u32, u32)> {
+    let mut xs: Vec<u32> = floats.iter().map(|f| f.x + f.width).collect();
+    xs.push(viewport.x);
+    xs.sort_unstable();
+    let mut ys: Vec<u32> = floats.iter().map(|f| f.y + f.height).collect();
+    ys.push(viewport.y);
+    ys.sort_unstable();
+
+    let fits = |x: u32, y: u32| {
+        x >= viewport.x
+            && y >= viewport.y
+            && x + width <= viewport.x + viewport.width
+            && y + height <= viewport.y + viewport.height
+            && !floats.iter().any(|f| {
+                x < f.x + f.width && f.x < x + width && y < f.y + f.height && f.y < y + height
+            })
+    };
+    ys.iter()
+        .flat_map(|&y| xs.iter().map(move |&x| (x, y)))
+        .find(|&(x, y)| fits(x, y))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const VIEWPORT: Viewport = Viewport {
+        x: 0,
+        y: 20,
+        width: 1000,
+        height: 600,
+        monitor: 0,
+    };
+
+    fn rect(x: u32, y: u32, width: u32, height: u32) -> Viewport {
+        Viewport {
+            x,
+            y,
+            width,
+            height,
+            monitor: 0,
+        }
+    }
+
+    #[test]
+    fn test_center() {
+        let place = FloatPlacement::Center.place(&VIEWPORT, (200, 100), None, &[]);
+        assert_eq!(place, (400, 270));
+    }
+
+    #[test]
+    fn test_mouse_is_kept_on_screen() {
+        let place = FloatPlacement::Mouse.place(&VIEWPORT, (200, 100), Some((500, 300)), &[]);
+        assert_eq!(place, (400, 250));
+        let place = FloatPlacement::Mouse.place(&VIEWPORT, (200, 100), Some((990, 5)), &[]);
+        assert_eq!(place, (800, 20));
+    }
+
+    #[test]
+    fn test_smart_avoids_floats() {
+        let floats = [rect(0, 20, 300, 200)];
+        let place = FloatPlacement::Smart.place(&VIEWPORT, (200, 100), None, &floats);
+        assert_eq!(place, (300, 20));
+        let floats = [rect(0, 20, 900, 200)];
+        let place = FloatPlacement::Smart.place(&VIEWPORT, (200, 100), None, &floats);
+        assert_eq!(place, (0, 220));
+    }
+
+    #[test]
+    fn test_smart_falls_back_to_center() {
+        let floats = [rect(0, 20, 1000, 600)];
+        let place = FloatPlacement::Smart.place(&VIEWPORT, (200, 100), None, &floats);
+        assert_eq!(place, (400, 270));
+    }
+}
@@ -0,0 +1,127 @@
+use crate::layout::{adjusted_gap, placements, Layout};
+use crate::stack::Stack;
+use crate::x::WindowId;
+use crate::Viewport;
+
+#[derive(Clone)]
+pub struct GridLayout {
+    name: String,
+    padding: u32,
+}
+
+impl GridLayout {
+    pub fn new<S: Into<String>>(name: S, padding: u32) -> GridLayout {
+        GridLayout {
+            name: name.into(),
+            padding,
+        }
+    }
+}
+
+/// Calculates the position of each of `count` windows in a roughly square grid
+/// as `(x, y, width, height)`.
+///
+/// The grid has `ceil(sqrt(count))` columns. If the last row has fewer windows
+/// than there are columns, its windows are stretched to fill the row.
+fn grid(viewport: &Viewport, padding: u32, count: usize) -> Vec<(u32, u32, u32, u32)> {
+    if count == 0 {
+        return Vec::new();
+    }
+
+    let count = count as u32;
+    let columns = f64::from(count).sqrt().ceil() as u32;
+    let rows = count.div_ceil(columns);
+    let tile_height = (viewport.height.saturating_sub(padding) / rows).saturating_sub(padding);
+
+    (0..count)
+        .map(|i| {
+            let row = i / columns;
+            let column = i % columns;
+            let row_columns = if row == rows - 1 {
+                count - (row * columns)
+            } else {
+                columns
+            };
+            let tile_width =
+                (viewport.width.saturating_sub(padding) / row_columns).saturating_sub(padding);
+            (
+                viewport.x + padding + (column * (tile_width + padding)),
+                viewport.y + padding + (row * (tile_height + padding)),
+                tile_width,
+                tile_height,
+            )
+        })
+        .collect()
+}
+
+impl Layout for GridLayout {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn adjust_gap(&mut self, delta: i32) {
+        self.padding = adjusted_gap(self.padding, delta);
+    }
+
+    fn set_gap(&mut self, gap: u32) {
+        self.padding = gap;
+    }
+
+    fn layout(&self, viewport: &Viewport, stack: &Stack<WindowId>) -> Vec<(WindowId, Viewport)> {
+        let geometry = grid(viewport, self.padding, stack.len());
+        placements(viewport, stack, geometry)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const VIEWPORT: Viewport = Viewport {
+        x: 0,
+        y: 0,
+        width: 1000,
+        height: 600,
+        monitor: 0,
+    };
+
+    #[test]
+    fn test_grid_one() {
+        assert_eq!(grid(&VIEWPORT, 10, 1), vec![(10, 10, 980, 580)]);
+    }
+
+    #[test]
+    fn test_grid_two() {
+        assert_eq!(
+            grid(&VIEWPORT, 10, 2),
+            vec![(10, 10, 485, 580), (505, 10, 485, 580)]
+        );
+    }
+
+    #[test]
+    fn test_grid_four() {
+        assert_eq!(
+            grid(&VIEWPORT, 10, 4),
+            vec![
+                (10, 10, 485, 285),
+                (505, 10, 485, 285),
+                (10, 305, 485, 285),
+                (505, 305, 485, 285),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_grid_five_stretches_last_row() {
+        assert_eq!(
+            grid(&VIEWPORT, 10, 5),
+            vec![
+                (10, 10, 320, 285),
+                (340, 10, 320, 285),
+                (670, 10, 320, 285),
+                (10, 305, 485, 285),
+                (505, 305, 485, 285),
+            ]
+        );
+    }
+}
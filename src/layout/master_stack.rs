@@ -0,0 +1,131 @@
+use crate::layout::{adjusted_gap, placements, Layout};
+use crate::stack::Stack;
+use crate::x::WindowId;
+use crate::Viewport;
+
+/// The smallest and largest fraction of the width the master column may take.
+const MIN_MASTER_RATIO: f32 = 0.1;
+const MAX_MASTER_RATIO: f32 = 0.9;
+
+#[derive(Clone)]
+pub struct MasterStackLayout {
+    name: String,
+    padding: u32,
+    master_ratio: f32,
+    master_on_right: bool,
+}
+
+impl MasterStackLayout {
+    /// Creates a layout with the first window in a master column on the left,
+    /// taking `master_ratio` of the width, and the others tiled vertically on
+    /// the right.
+    ///
+    /// `master_ratio` is clamped as by `set_master_ratio()`.
+    pub fn new<S: Into<String>>(name: S, padding: u32, master_ratio: f32) -> MasterStackLayout {
+        MasterStackLayout {
+            name: name.into(),
+            padding,
+            master_ratio: master_ratio.clamp(MIN_MASTER_RATIO, MAX_MASTER_RATIO),
+            master_on_right: false,
+        }
+    }
+
+    /// Puts the master column on the right, with the other windows on the
+    /// left, e.g. for left-handed mouse users.
+    pub fn master_on_right(mut self, on_right: bool) -> MasterStackLayout {
+        self.master_on_right = on_right;
+        self
+    }
+
+    /// Calculates the position of each of `count` windows as `(x, y, width,
+    /// height)`.
+    ///
+    /// Padding is subtracted with saturating arithmetic, so that a large
+    /// padding or a small viewport results in zero-sized windows rather than
+    /// underflowing.
+    fn geometry(&self, viewport: &Viewport, count: usize) -> Vec<(u32, u32, u32, u32)> {
+        let padding = self.padding;
+        let inner_height = viewport.height.saturating_sub(padding * 2);
+
+        if count == 1 {
+            return vec![(
+                viewport.x + padding,
+                viewport.y + padding,
+                viewport.width.saturating_sub(padding * 2),
+                inner_height,
+            )];
+        }
+
+        let master_width = (viewport.width as f32 * self.master_ratio) as u32;
+        let mut geometry = vec![(
+            viewport.x + padding,
+            viewport.y + padding,
+            master_width.saturating_sub(padding * 2),
+            inner_height,
+        )];
+
+        // The right-hand column shares the padding between it and the master
+        // column, as TiledLayout does between tiles.
+        let others = (count - 1) as u32;
+        let column_x = viewport.x + master_width;
+        let column_width = viewport
+            .width
+            .saturating_sub(master_width)
+            .saturating_sub(padding);
+        let tile_height =
+            (viewport.height.saturating_sub(padding) / others).saturating_sub(padding);
+        for i in 0..others {
+            geometry.push((
+                column_x,
+                viewport.y + padding + (i * (tile_height + padding)),
+                column_width,
+                tile_height,
+            ));
+        }
+        geometry
+    }
+}
+
+impl Layout for MasterStackLayout {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn adjust_gap(&mut self, delta: i32) {
+        self.padding = adjusted_gap(self.padding, delta);
+    }
+
+    fn set_gap(&mut self, gap: u32) {
+        self.padding = gap;
+    }
+
+    fn adjust_master_ratio(&mut self, delta: f32) {
+        self.set_master_ratio(self.master_ratio + delta);
+    }
+
+    fn set_master_ratio(&mut self, ratio: f32) {
+        self.master_ratio = ratio.clamp(MIN_MASTER_RATIO, MAX_MASTER_RATIO);
+    }
+
+    fn toggle_master_side(&mut self) {
+        self.master_on_right = !self.master_on_right;
+    }
+
+    fn layout(&self, viewport: &Viewport, stack: &Stack<WindowId>) -> Vec<(WindowId, Viewport)> {
+        if stack.is_empty() {
+            return Vec::new();
+        }
+
+        let geometry = self.geometry(viewport, stack.len());
+        if self.master_on_right {
+            // Mirror the left-master arrangement across the viewport.
+            let right = 2 * viewport.x + viewport.width;
+            let mirrored = geometry
+                .into_iter()
+                .map(|(x, y, width, height)| (right.saturating_sub(x + width), y, width, height));
+            placements(viewport, stack, mirrored)
+        } else {
+            placements(viewport, stack, geometry)
+        }
+    }
+}
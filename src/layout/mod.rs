@@ -1,15 +1,48 @@
+use std::cmp;
 use std::fmt;
 
 use crate::stack::Stack;
 use crate::x::{Connection, WindowId};
 use crate::Viewport;
 
+mod columns;
+mod float;
+mod grid;
+mod master_stack;
+mod monocle;
+mod side_pane;
+mod sidebar;
 mod stack;
 mod tiled;
 
+pub use self::columns::ColumnsLayout;
+pub use self::float::FloatPlacement;
+pub use self::grid::GridLayout;
+pub use self::master_stack::MasterStackLayout;
+pub use self::monocle::MonocleLayout;
+pub(crate) use self::side_pane::SidePane;
+pub use self::sidebar::SidebarLayout;
 pub use self::stack::StackLayout;
 pub use self::tiled::TiledLayout;
 
+/// The axis along which a layout arranges windows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Orientation {
+    /// Windows are stacked on top of each other, each taking the full width.
+    Vertical,
+    /// Windows are side-by-side, each taking the full height.
+    Horizontal,
+}
+
+impl Orientation {
+    pub fn flipped(self) -> Orientation {
+        match self {
+            Orientation::Vertical => Orientation::Horizontal,
+            Orientation::Horizontal => Orientation::Vertical,
+        }
+    }
+}
+
 pub trait LayoutClone {
     fn clone_box(&self) -> Box<dyn Layout>;
 }
@@ -25,7 +58,244 @@ where
 
 pub trait Layout: LayoutClone {
     fn name(&self) -> &str;
-    fn layout(&self, connection: &Connection, viewport: &Viewport, stack: &Stack<WindowId>);
+
+    /// Calculates where each window in `stack` should be placed within
+    /// `viewport`.
+    ///
+    /// Windows which aren't given a placement are unmapped.
+    fn layout(&self, viewport: &Viewport, stack: &Stack<WindowId>) -> Vec<(WindowId, Viewport)>;
+
+    /// Lets the layout look up anything it needs to know about the windows
+    /// before they're laid out, as `layout()` has no access to the X server.
+    ///
+    /// Layouts which only need the stack can ignore this.
+    fn inspect_windows(&mut self, _connection: &Connection, _stack: &Stack<WindowId>) {}
+
+    /// Flips the layout between arranging windows vertically and horizontally.
+    ///
+    /// Layouts without an orientation can ignore this.
+    fn toggle_orientation(&mut self) {}
+
+    /// Moves the master area to the other side of the layout.
+    ///
+    /// Layouts without a master area can ignore this.
+    fn toggle_master_side(&mut self) {}
+
+    /// Sets whether the layout arranges windows vertically or horizontally.
+    ///
+    /// Layouts without an orientation can ignore this.
+    fn set_orientation(&mut self, _orientation: Orientation) {}
+
+    /// Grows or shrinks the gap the layout leaves around windows by `delta`
+    /// pixels, never going below zero.
+    ///
+    /// Layouts without a gap can ignore this.
+    fn adjust_gap(&mut self, _delta: i32) {}
+
+    /// Grows or shrinks the fraction of the width given to the master area by
+    /// `delta`.
+    ///
+    /// Layouts without a master area can ignore this.
+    fn adjust_master_ratio(&mut self, _delta: f32) {}
+
+    /// Sets the gap the layout leaves around windows, in pixels.
+    ///
+    /// Layouts without a gap can ignore this.
+    fn set_gap(&mut self, _gap: u32) {}
+
+    /// Sets the fraction of the width given to the master area.
+    ///
+    /// Layouts without a master area can ignore this.
+    fn set_master_ratio(&mut self, _ratio: f32) {}
+}
+
+/// A layout along with the parameters it should use, so that they can be
+/// applied to a group together (e.g. a "reading" preset of a gapless
+/// monocle layout).
+#[derive(Clone, Debug)]
+pub struct LayoutPreset {
+    name: String,
+    layout: String,
+    gap: Option<u32>,
+    master_ratio: Option<f32>,
+}
+
+impl LayoutPreset {
+    /// Creates a preset named `name`, which switches to the layout named
+    /// `layout` without changing its parameters.
+    pub fn new<S1, S2>(name: S1, layout: S2) -> LayoutPreset
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        LayoutPreset {
+            name: name.into(),
+            layout: layout.into(),
+            gap: None,
+            master_ratio: None,
+        }
+    }
+
+    pub fn gap(mut self, gap: u32) -> LayoutPreset {
+        self.gap = Some(gap);
+        self
+    }
+
+    pub fn master_ratio(mut self, ratio: f32) -> LayoutPreset {
+        self.master_ratio = Some(ratio);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn layout(&self) -> &str {
+        &self.layout
+    }
+
+    /// Applies the preset's parameters to the layout.
+    pub fn configure(&self, layout: &mut dyn Layout) {
+        if let Some(gap) = self.gap {
+            layout.set_gap(gap);
+        }
+        if let Some(ratio) = self.master_ratio {
+            layout.set_master_ratio(ratio);
+        }
+    }
+}
+
+/// Splits a column `width` pixels wide off the right of the viewport, returning
+/// the remaining viewport and the column.
+fn split_right(viewport: &Viewport, width: u32) -> (Viewport, Viewport) {
+    let width = cmp::min(width, viewport.width);
+    let remaining = Viewport {
+        width: viewport.width - width,
+        ..*viewport
+    };
+    let column = Viewport {
+        x: viewport.x + remaining.width,
+        width,
+        ..*viewport
+    };
+    (remaining, column)
+}
+
+/// Returns the largest part of the viewport which lies entirely to one side
+/// of `area`, so that windows can be tiled around a window occupying it.
+pub(crate) fn reserve(viewport: &Viewport, area: &Viewport) -> Viewport {
+    let left = cmp::max(area.x, viewport.x);
+    let top = cmp::max(area.y, viewport.y);
+    let right = cmp::min(area.x + area.width, viewport.x + viewport.width);
+    let bottom = cmp::min(area.y + area.height, viewport.y + viewport.height);
+    if left >= right || top >= bottom {
+        return *viewport;
+    }
+
+    let candidates = [
+        Viewport {
+            width: left - viewport.x,
+            ..*viewport
+        },
+        Viewport {
+            x: right,
+            width: viewport.x + viewport.width - right,
+            ..*viewport
+        },
+        Viewport {
+            height: top - viewport.y,
+            ..*viewport
+        },
+        Viewport {
+            y: bottom,
+            height: viewport.y + viewport.height - bottom,
+            ..*viewport
+        },
+    ];
+    candidates
+        .iter()
+        .copied()
+        .max_by_key(|candidate| u64::from(candidate.width) * u64::from(candidate.height))
+        .unwrap()
+}
+
+/// Rotates a placement within `viewport` by 90°, swapping its axes and scaling
+/// them to the viewport, so that e.g. a column on the left becomes a row along
+/// the top.
+pub(crate) fn rotate(viewport: &Viewport, placement: &Viewport) -> Viewport {
+    fn scale(value: u32, to: u32, from: u32) -> u32 {
+        if from == 0 {
+            return 0;
+        }
+        (u64::from(value) * u64::from(to) / u64::from(from)) as u32
+    }
+
+    let x = placement.x.saturating_sub(viewport.x);
+    let y = placement.y.saturating_sub(viewport.y);
+    Viewport {
+        x: viewport.x + scale(y, viewport.width, viewport.height),
+        y: viewport.y + scale(x, viewport.height, viewport.width),
+        width: scale(placement.height, viewport.width, viewport.height),
+        height: scale(placement.width, viewport.height, viewport.width),
+        ..*placement
+    }
+}
+
+/// Places the windows in `stack` at the corresponding `(x, y, width, height)`
+/// in `geometry`, within `viewport`.
+fn placements<I>(
+    viewport: &Viewport,
+    stack: &Stack<WindowId>,
+    geometry: I,
+) -> Vec<(WindowId, Viewport)>
+where
+    I: IntoIterator<Item = (u32, u32, u32, u32)>,
+{
+    stack
+        .iter()
+        .zip(geometry)
+        .map(|(window_id, (x, y, width, height))| {
+            let placement = Viewport {
+                x,
+                y,
+                width,
+                height,
+                ..*viewport
+            };
+            (*window_id, placement)
+        })
+        .collect()
+}
+
+/// Shows only the focused window, filling the viewport less `padding` on each
+/// side, leaving the others to be unmapped.
+fn layout_focused(
+    viewport: &Viewport,
+    padding: u32,
+    stack: &Stack<WindowId>,
+) -> Vec<(WindowId, Viewport)> {
+    let focused_id = match stack.focused() {
+        Some(focused_id) => focused_id,
+        None => return Vec::new(),
+    };
+
+    let placement = Viewport {
+        x: viewport.x + padding,
+        y: viewport.y + padding,
+        width: viewport.width.saturating_sub(padding * 2),
+        height: viewport.height.saturating_sub(padding * 2),
+        ..*viewport
+    };
+    vec![(*focused_id, placement)]
+}
+
+/// Adjusts a gap by `delta` pixels, clamping it at zero.
+fn adjusted_gap(gap: u32, delta: i32) -> u32 {
+    if delta < 0 {
+        gap.saturating_sub(delta.unsigned_abs())
+    } else {
+        gap.saturating_add(delta as u32)
+    }
 }
 
 impl Clone for Box<dyn Layout> {
@@ -39,3 +309,194 @@ impl fmt::Debug for dyn Layout {
         write!(f, "Layout {{ \"{}\" }}", self.name())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn viewport(x: u32, y: u32, width: u32, height: u32) -> Viewport {
+        Viewport {
+            x,
+            y,
+            width,
+            height,
+            monitor: 0,
+        }
+    }
+
+    fn rect(viewport: Viewport) -> (u32, u32, u32, u32) {
+        (viewport.x, viewport.y, viewport.width, viewport.height)
+    }
+
+    fn stack(count: u32) -> Stack<WindowId> {
+        Stack::from((1..=count).map(WindowId::from).collect::<Vec<_>>())
+    }
+
+    fn rects(placements: Vec<(WindowId, Viewport)>) -> Vec<(u32, u32, u32, u32)> {
+        placements
+            .into_iter()
+            .map(|(_, placement)| rect(placement))
+            .collect()
+    }
+
+    #[test]
+    fn test_master_stack_on_right_mirrors() {
+        let screen = viewport(100, 0, 1000, 600);
+        let mut layout = MasterStackLayout::new("master", 10, 0.6).master_on_right(true);
+        assert_eq!(
+            rects(layout.layout(&screen, &stack(3))),
+            vec![
+                (510, 10, 580, 580),
+                (110, 10, 390, 285),
+                (110, 305, 390, 285),
+            ]
+        );
+        layout.toggle_master_side();
+        assert_eq!(
+            rects(layout.layout(&screen, &stack(3)))[0],
+            (110, 10, 580, 580)
+        );
+    }
+
+    #[test]
+    fn test_master_stack_layout() {
+        let screen = viewport(0, 0, 1000, 600);
+        let layout = MasterStackLayout::new("master", 10, 0.5);
+        assert_eq!(
+            rects(layout.layout(&screen, &stack(1))),
+            vec![(10, 10, 980, 580)]
+        );
+        assert_eq!(
+            rects(layout.layout(&screen, &stack(3))),
+            vec![
+                (10, 10, 480, 580),
+                (500, 10, 490, 285),
+                (500, 305, 490, 285)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_master_stack_clamps_ratio() {
+        let screen = viewport(0, 0, 1000, 600);
+        let layout = MasterStackLayout::new("master", 0, 2.0);
+        let mut clamped = MasterStackLayout::new("master", 0, 0.5);
+        clamped.set_master_ratio(2.0);
+        assert_eq!(
+            rects(layout.layout(&screen, &stack(2))),
+            rects(clamped.layout(&screen, &stack(2)))
+        );
+        assert_eq!(
+            rects(layout.layout(&screen, &stack(2)))[0],
+            (0, 0, 900, 600)
+        );
+    }
+
+    #[test]
+    fn test_grid_layout_stretches_last_row() {
+        let screen = viewport(0, 0, 1000, 600);
+        let layout = GridLayout::new("grid", 10);
+        assert_eq!(
+            rects(layout.layout(&screen, &stack(3))),
+            vec![(10, 10, 485, 285), (505, 10, 485, 285), (10, 305, 980, 285)]
+        );
+    }
+
+    #[test]
+    fn test_side_pane_layout() {
+        let screen = viewport(0, 0, 1000, 600);
+        let inner = GridLayout::new("grid", 0);
+        let pane = SidePane::new(WindowId::from(2), 0.25);
+        let placements = pane.layout(&inner, &screen, &stack(3));
+        assert_eq!(
+            placements.iter().map(|(w, _)| *w).collect::<Vec<_>>(),
+            vec![WindowId::from(1), WindowId::from(3), WindowId::from(2)]
+        );
+        assert_eq!(
+            rects(placements),
+            vec![(0, 0, 375, 600), (375, 0, 375, 600), (750, 0, 250, 600)]
+        );
+    }
+
+    #[test]
+    fn test_side_pane_without_window_uses_inner_layout() {
+        let screen = viewport(0, 0, 1000, 600);
+        let inner = GridLayout::new("grid", 0);
+        let pane = SidePane::new(WindowId::from(4), 0.25);
+        assert_eq!(
+            rects(pane.layout(&inner, &screen, &stack(1))),
+            vec![(0, 0, 1000, 600)]
+        );
+    }
+
+    #[test]
+    fn test_preset_configure() {
+        let screen = viewport(0, 0, 1000, 600);
+        let mut layout = MasterStackLayout::new("master", 10, 0.5);
+        LayoutPreset::new("coding", "master")
+            .gap(0)
+            .master_ratio(0.7)
+            .configure(&mut layout);
+        assert_eq!(
+            rects(layout.layout(&screen, &stack(2))),
+            vec![(0, 0, 700, 600), (700, 0, 300, 600)]
+        );
+    }
+
+    #[test]
+    fn test_preset_configure_keeps_unset_parameters() {
+        let screen = viewport(0, 0, 1000, 600);
+        let mut layout = MasterStackLayout::new("master", 10, 0.5);
+        LayoutPreset::new("default", "master").configure(&mut layout);
+        assert_eq!(
+            rects(layout.layout(&screen, &stack(2))),
+            rects(MasterStackLayout::new("master", 10, 0.5).layout(&screen, &stack(2)))
+        );
+        LayoutPreset::new("gapless", "master")
+            .gap(0)
+            .configure(&mut layout);
+        assert_eq!(
+            rects(layout.layout(&screen, &stack(2)))[0],
+            (0, 0, 500, 600)
+        );
+    }
+
+    #[test]
+    fn test_rotate_master_to_top() {
+        let screen = viewport(0, 0, 1000, 500);
+        let layout = MasterStackLayout::new("master", 0, 0.6);
+        let rotated: Vec<_> = layout
+            .layout(&screen, &stack(3))
+            .iter()
+            .map(|(_, placement)| rect(rotate(&screen, placement)))
+            .collect();
+        assert_eq!(
+            rotated,
+            vec![(0, 0, 1000, 300), (0, 300, 500, 200), (500, 300, 500, 200)]
+        );
+    }
+
+    #[test]
+    fn test_rotate_twice_restores_square() {
+        let screen = viewport(100, 100, 800, 800);
+        let placement = viewport(150, 300, 200, 400);
+        let rotated = rotate(&screen, &rotate(&screen, &placement));
+        assert_eq!(rect(rotated), rect(placement));
+    }
+
+    #[test]
+    fn test_reserve_keeps_largest_side() {
+        let screen = viewport(0, 20, 1000, 800);
+        let reserved = reserve(&screen, &viewport(50, 100, 300, 400));
+        assert_eq!(rect(reserved), (350, 20, 650, 800));
+        let reserved = reserve(&screen, &viewport(0, 500, 1000, 320));
+        assert_eq!(rect(reserved), (0, 20, 1000, 480));
+    }
+
+    #[test]
+    fn test_reserve_ignores_area_outside_viewport() {
+        let screen = viewport(0, 0, 1000, 800);
+        let reserved = reserve(&screen, &viewport(1200, 0, 100, 100));
+        assert_eq!(rect(reserved), (0, 0, 1000, 800));
+    }
+}
@@ -0,0 +1,41 @@
+use crate::layout::{adjusted_gap, layout_focused, Layout};
+use crate::stack::Stack;
+use crate::x::WindowId;
+use crate::Viewport;
+
+/// Shows only the focused window, filling the whole viewport.
+///
+/// This behaves like `StackLayout`, but has no padding unless it is added
+/// with `adjust_gap()`.
+#[derive(Clone)]
+pub struct MonocleLayout {
+    name: String,
+    padding: u32,
+}
+
+impl MonocleLayout {
+    pub fn new<S: Into<String>>(name: S) -> MonocleLayout {
+        MonocleLayout {
+            name: name.into(),
+            padding: 0,
+        }
+    }
+}
+
+impl Layout for MonocleLayout {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn adjust_gap(&mut self, delta: i32) {
+        self.padding = adjusted_gap(self.padding, delta);
+    }
+
+    fn set_gap(&mut self, gap: u32) {
+        self.padding = gap;
+    }
+
+    fn layout(&self, viewport: &Viewport, stack: &Stack<WindowId>) -> Vec<(WindowId, Viewport)> {
+        layout_focused(viewport, self.padding, stack)
+    }
+}
@@ -0,0 +1,46 @@
+use crate::layout::{split_right, Layout};
+use crate::stack::Stack;
+use crate::x::WindowId;
+use crate::Viewport;
+
+/// Pins a single window to a pane on the right of the viewport, while another
+/// layout arranges the rest of the stack in the remaining space.
+///
+/// This wraps whichever layout a group currently has focused, so it isn't a
+/// `Layout` in its own right.
+#[derive(Clone, Debug)]
+pub struct SidePane {
+    window_id: WindowId,
+    ratio: f32,
+}
+
+impl SidePane {
+    /// Pins `window_id` to a pane taking up `ratio` of the viewport's width.
+    pub fn new(window_id: WindowId, ratio: f32) -> SidePane {
+        SidePane { window_id, ratio }
+    }
+
+    pub fn window_id(&self) -> &WindowId {
+        &self.window_id
+    }
+
+    pub fn layout(
+        &self,
+        inner: &dyn Layout,
+        viewport: &Viewport,
+        stack: &Stack<WindowId>,
+    ) -> Vec<(WindowId, Viewport)> {
+        // If the pinned window isn't visible, fall back to the wrapped layout.
+        if !stack.iter().any(|w| w == &self.window_id) {
+            return inner.layout(viewport, stack);
+        }
+
+        let pane_width = (viewport.width as f32 * self.ratio) as u32;
+        let (main_viewport, pane) = split_right(viewport, pane_width);
+
+        let rest = stack.filtered(|w| w != &self.window_id);
+        let mut placements = inner.layout(&main_viewport, &rest);
+        placements.push((self.window_id, pane));
+        placements
+    }
+}
@@ -0,0 +1,148 @@
+use crate::layout::{split_right, Layout, Orientation};
+use crate::stack::Stack;
+use crate::x::{Connection, WindowId};
+use crate::Viewport;
+
+/// Keeps the first window with a matching `WM_CLASS` in a fixed-width column
+/// on the right of the screen, and arranges the rest of the windows using
+/// another layout.
+///
+/// If no window matches, the other layout is used for the whole screen.
+#[derive(Clone)]
+pub struct SidebarLayout {
+    name: String,
+    class: String,
+    width: u32,
+    inner: Box<dyn Layout>,
+    /// The window found by `inspect_windows()` to keep in the sidebar.
+    sidebar_id: Option<WindowId>,
+}
+
+impl SidebarLayout {
+    /// Creates a layout that puts windows whose `WM_CLASS` instance or class
+    /// name is `class` in a sidebar `width` pixels wide, with the remaining
+    /// windows arranged by `inner`.
+    pub fn new<S1, S2, L>(name: S1, class: S2, width: u32, inner: L) -> SidebarLayout
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+        L: Layout + 'static,
+    {
+        SidebarLayout {
+            name: name.into(),
+            class: class.into(),
+            width,
+            inner: Box::new(inner),
+            sidebar_id: None,
+        }
+    }
+}
+
+impl Layout for SidebarLayout {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn toggle_orientation(&mut self) {
+        self.inner.toggle_orientation();
+    }
+
+    fn set_orientation(&mut self, orientation: Orientation) {
+        self.inner.set_orientation(orientation);
+    }
+
+    fn adjust_gap(&mut self, delta: i32) {
+        self.inner.adjust_gap(delta);
+    }
+
+    fn toggle_master_side(&mut self) {
+        self.inner.toggle_master_side();
+    }
+
+    fn adjust_master_ratio(&mut self, delta: f32) {
+        self.inner.adjust_master_ratio(delta);
+    }
+
+    fn set_gap(&mut self, gap: u32) {
+        self.inner.set_gap(gap);
+    }
+
+    fn set_master_ratio(&mut self, ratio: f32) {
+        self.inner.set_master_ratio(ratio);
+    }
+
+    fn inspect_windows(&mut self, connection: &Connection, stack: &Stack<WindowId>) {
+        self.sidebar_id = stack
+            .iter()
+            .find(|window_id| {
+                connection.get_wm_class(window_id).is_some_and(|wm_class| {
+                    wm_class.instance == self.class || wm_class.class == self.class
+                })
+            })
+            .cloned();
+        self.inner.inspect_windows(connection, stack);
+    }
+
+    fn layout(&self, viewport: &Viewport, stack: &Stack<WindowId>) -> Vec<(WindowId, Viewport)> {
+        let sidebar_id = match self.sidebar_id {
+            Some(ref sidebar_id) if stack.iter().any(|w| w == sidebar_id) => sidebar_id,
+            _ => return self.inner.layout(viewport, stack),
+        };
+
+        let (main_viewport, sidebar) = split_right(viewport, self.width);
+
+        let rest = stack.filtered(|window_id| window_id != sidebar_id);
+        let mut placements = self.inner.layout(&main_viewport, &rest);
+        placements.push((*sidebar_id, sidebar));
+        placements
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::layout::GridLayout;
+
+    const VIEWPORT: Viewport = Viewport {
+        x: 0,
+        y: 0,
+        width: 1000,
+        height: 600,
+        monitor: 0,
+    };
+
+    fn layout(sidebar_id: Option<u32>, count: u32) -> Vec<(WindowId, (u32, u32, u32, u32))> {
+        let mut sidebar = SidebarLayout::new("sidebar", "slack", 200, GridLayout::new("grid", 0));
+        sidebar.sidebar_id = sidebar_id.map(WindowId::from);
+        let stack = Stack::from((1..=count).map(WindowId::from).collect::<Vec<_>>());
+        sidebar
+            .layout(&VIEWPORT, &stack)
+            .into_iter()
+            .map(|(w, p)| (w, (p.x, p.y, p.width, p.height)))
+            .collect()
+    }
+
+    #[test]
+    fn test_sidebar_on_right() {
+        assert_eq!(
+            layout(Some(1), 2),
+            vec![
+                (WindowId::from(2), (0, 0, 800, 600)),
+                (WindowId::from(1), (800, 0, 200, 600)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_no_sidebar_uses_inner_layout() {
+        assert_eq!(
+            layout(None, 1),
+            vec![(WindowId::from(1), (0, 0, 1000, 600))]
+        );
+        // The sidebar window may have been removed since it was found.
+        assert_eq!(
+            layout(Some(5), 1),
+            vec![(WindowId::from(1), (0, 0, 1000, 600))]
+        );
+    }
+}
@@ -1,6 +1,6 @@
-use crate::layout::Layout;
+use crate::layout::{adjusted_gap, layout_focused, Layout};
 use crate::stack::Stack;
-use crate::x::{Connection, WindowId};
+use crate::x::WindowId;
 use crate::Viewport;
 
 #[derive(Clone)]
@@ -23,32 +23,15 @@ impl Layout for StackLayout {
         &self.name
     }
 
-    fn layout(&self, connection: &Connection, viewport: &Viewport, stack: &Stack<WindowId>) {
-        if stack.is_empty() {
-            return;
-        }
-
-        // A non-empty `Stack` is guaranteed to have something focused.
-        let focused_id = stack.focused().unwrap();
+    fn adjust_gap(&mut self, delta: i32) {
+        self.padding = adjusted_gap(self.padding, delta);
+    }
 
-        for window_id in stack.iter() {
-            if focused_id == window_id {
-                continue;
-            }
-            connection.disable_window_tracking(window_id);
-            connection.unmap_window(window_id);
-            connection.enable_window_tracking(window_id);
-        }
+    fn set_gap(&mut self, gap: u32) {
+        self.padding = gap;
+    }
 
-        connection.disable_window_tracking(focused_id);
-        connection.map_window(focused_id);
-        connection.configure_window(
-            focused_id,
-            viewport.x + self.padding,
-            viewport.y + self.padding,
-            viewport.width - (self.padding * 2),
-            viewport.height - (self.padding * 2),
-        );
-        connection.enable_window_tracking(focused_id);
+    fn layout(&self, viewport: &Viewport, stack: &Stack<WindowId>) -> Vec<(WindowId, Viewport)> {
+        layout_focused(viewport, self.padding, stack)
     }
 }
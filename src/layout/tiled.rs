@@ -1,12 +1,13 @@
-use crate::layout::Layout;
+use crate::layout::{adjusted_gap, placements, Layout, Orientation};
 use crate::stack::Stack;
-use crate::x::{Connection, WindowId};
+use crate::x::WindowId;
 use crate::Viewport;
 
 #[derive(Clone)]
 pub struct TiledLayout {
     name: String,
     padding: u32,
+    orientation: Orientation,
 }
 
 impl TiledLayout {
@@ -14,33 +15,121 @@ impl TiledLayout {
         TiledLayout {
             name: name.into(),
             padding,
+            orientation: Orientation::Vertical,
         }
     }
 }
 
+/// Calculates the position of each of `count` tiles as `(x, y, width,
+/// height)`, with `padding` around and between them.
+///
+/// Tiles which don't fit in the viewport get a width or height of zero.
+pub(super) fn tiles(
+    viewport: &Viewport,
+    padding: u32,
+    orientation: Orientation,
+    count: usize,
+) -> Vec<(u32, u32, u32, u32)> {
+    if count == 0 {
+        return Vec::new();
+    }
+
+    let count = count as u32;
+    (0..count)
+        .map(|i| match orientation {
+            Orientation::Vertical => {
+                let tile_height =
+                    (viewport.height.saturating_sub(padding) / count).saturating_sub(padding);
+                (
+                    viewport.x + padding,
+                    viewport.y + padding + (i * (tile_height + padding)),
+                    viewport.width.saturating_sub(padding * 2),
+                    tile_height,
+                )
+            }
+            Orientation::Horizontal => {
+                let tile_width =
+                    (viewport.width.saturating_sub(padding) / count).saturating_sub(padding);
+                (
+                    viewport.x + padding + (i * (tile_width + padding)),
+                    viewport.y + padding,
+                    tile_width,
+                    viewport.height.saturating_sub(padding * 2),
+                )
+            }
+        })
+        .collect()
+}
+
 impl Layout for TiledLayout {
     fn name(&self) -> &str {
         &self.name
     }
 
-    fn layout(&self, connection: &Connection, viewport: &Viewport, stack: &Stack<WindowId>) {
-        if stack.is_empty() {
-            return;
-        }
+    fn adjust_gap(&mut self, delta: i32) {
+        self.padding = adjusted_gap(self.padding, delta);
+    }
 
-        let tile_height = ((viewport.height - self.padding) / stack.len() as u32) - self.padding;
-
-        for (i, window_id) in stack.iter().enumerate() {
-            connection.disable_window_tracking(window_id);
-            connection.map_window(window_id);
-            connection.configure_window(
-                window_id,
-                viewport.x + self.padding,
-                viewport.y + self.padding + (i as u32 * (tile_height + self.padding)),
-                viewport.width - (self.padding * 2),
-                tile_height,
-            );
-            connection.enable_window_tracking(window_id);
-        }
+    fn set_gap(&mut self, gap: u32) {
+        self.padding = gap;
+    }
+
+    fn toggle_orientation(&mut self) {
+        self.orientation = self.orientation.flipped();
+    }
+
+    fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+
+    fn layout(&self, viewport: &Viewport, stack: &Stack<WindowId>) -> Vec<(WindowId, Viewport)> {
+        let geometry = tiles(viewport, self.padding, self.orientation, stack.len());
+        // Windows which don't fit in a tiny viewport are left unmapped.
+        placements(viewport, stack, geometry)
+            .into_iter()
+            .filter(|(_, placement)| placement.width > 0 && placement.height > 0)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const VIEWPORT: Viewport = Viewport {
+        x: 0,
+        y: 0,
+        width: 1000,
+        height: 600,
+        monitor: 0,
+    };
+
+    #[test]
+    fn test_tiles_vertical() {
+        assert_eq!(
+            tiles(&VIEWPORT, 10, Orientation::Vertical, 2),
+            vec![(10, 10, 980, 285), (10, 305, 980, 285)]
+        );
+    }
+
+    #[test]
+    fn test_tiles_horizontal() {
+        assert_eq!(
+            tiles(&VIEWPORT, 10, Orientation::Horizontal, 2),
+            vec![(10, 10, 485, 580), (505, 10, 485, 580)]
+        );
+    }
+
+    #[test]
+    fn test_tiles_padding_larger_than_viewport() {
+        let viewport = Viewport {
+            width: 100,
+            height: 100,
+            ..VIEWPORT
+        };
+        assert_eq!(
+            tiles(&viewport, 60, Orientation::Vertical, 3),
+            vec![(60, 60, 0, 0), (60, 120, 0, 0), (60, 180, 0, 0)]
+        );
     }
 }
@@ -5,25 +5,38 @@ extern crate log;
 
 use std::cell::RefCell;
 use std::cmp;
+use std::os::unix::process::CommandExt;
+use std::process;
 use std::rc::Rc;
+use std::time::Duration;
 
-use failure::{Error, ResultExt};
+use failure::{format_err, Error, ResultExt};
 
 pub mod cmd;
 mod groups;
 mod keys;
 pub mod layout;
+mod rules;
+mod session;
 mod stack;
+pub mod text;
 mod x;
 
-use crate::groups::Group;
-use crate::keys::{KeyCombo, KeyHandlers};
-use crate::layout::Layout;
-use crate::x::{Connection, Event, StrutPartial, WindowId, WindowType};
+use crate::cmd::Command;
+use crate::groups::{Group, Layer};
+use crate::keys::{ButtonCombo, KeyCombo, KeyHandlers, MouseHandlers};
+use crate::layout::{FloatPlacement, Layout, LayoutPreset};
+use crate::rules::WindowRule;
+use crate::session::{GroupSession, Session, WindowSession};
+use crate::x::{
+    Atom, Connection, Event, StateAction, Strut, StrutPartial, Timestamp, WindowId, WindowState,
+    WindowType, WmState,
+};
 
-pub use crate::groups::GroupBuilder;
-pub use crate::keys::ModKey;
+pub use crate::groups::{Borders, GroupBuilder, GroupSummary};
+pub use crate::keys::{KeyTrigger, ModKey, MouseAction};
 pub use crate::stack::Stack;
+pub use crate::x::FocusRevert;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -33,17 +46,14 @@ pub mod keysym {
 
 /// Initializes a logger using the default configuration.
 ///
-/// Outputs to stdout and `$XDG_DATA/lanta/lanta.log` by default.
+/// Outputs to stdout and `$XDG_DATA/lanta/lanta.log` by default. If the log
+/// file can't be created (e.g. because the XDG directories are misconfigured),
+/// a warning is logged and only stdout is used.
 /// You should feel free to initialize your own logger, instead of using this.
 pub fn intiailize_logger() -> Result<()> {
     log_panics::init();
 
-    let xdg_dirs = xdg::BaseDirectories::with_prefix("lanta")?;
-    let log_path = xdg_dirs
-        .place_data_file("lanta.log")
-        .context("Could not create log file")?;
-
-    fern::Dispatch::new()
+    let dispatch = fern::Dispatch::new()
         .format(|out, message, record| {
             out.finish(format_args!(
                 "[{}] [{}] {}",
@@ -53,20 +63,47 @@ pub fn intiailize_logger() -> Result<()> {
             ))
         })
         .level(log::LevelFilter::Trace)
-        .chain(std::io::stdout())
-        .chain(fern::log_file(&log_path)?)
-        .apply()?;
+        .chain(std::io::stdout());
+
+    // Losing the log file shouldn't stop the WM from starting.
+    match log_file() {
+        Ok(log_file) => dispatch.chain(log_file).apply()?,
+        Err(error) => {
+            dispatch.apply()?;
+            warn!("Logging to stdout only: {}", error);
+        }
+    }
 
     Ok(())
 }
 
+fn log_file() -> Result<std::fs::File> {
+    let xdg_dirs = xdg::BaseDirectories::with_prefix("lanta")?;
+    let log_path = xdg_dirs
+        .place_data_file("lanta.log")
+        .context("Could not create log file")?;
+    Ok(fern::log_file(&log_path)?)
+}
+
+/// Synthesizes a press (or release) of the key for `keysym` on the X server
+/// in `$DISPLAY`, using the XTEST extension.
+///
+/// This lets integration tests drive the keybindings of a lanta running in a
+/// nested X server (e.g. Xephyr) from another process.
+#[cfg(feature = "xtest")]
+pub fn send_fake_key(keysym: u32, press: bool) -> Result<()> {
+    Connection::connect()?.send_fake_key(keysym, press)
+}
+
 #[macro_export]
 macro_rules! keys {
-    [ $( ([$( $mod:ident ),*], $key:ident, $cmd:expr) ),+ $(,)*] => (
+    [ $( ([$( $mod:ident ),*], $key:ident, $cmd:expr $(, $trigger:expr)?) ),+ $(,)*] => (
         vec![
-            $( (vec![$( $mod ),*],  $crate::keysym::$key, $cmd) ),+
+            $( (vec![$( $mod ),*],  $crate::keysym::$key, $cmd, $crate::keys!(@trigger $($trigger)?)) ),+
         ]
-    )
+    );
+    (@trigger) => ($crate::KeyTrigger::Press);
+    (@trigger $trigger:expr) => ($trigger);
 }
 
 #[macro_export]
@@ -114,31 +151,67 @@ pub struct Viewport {
     pub y: u32,
     pub width: u32,
     pub height: u32,
+    /// The index of the monitor the geometry is on.
+    pub monitor: usize,
 }
 
 struct Dock {
     window_id: WindowId,
     strut_partial: Option<StrutPartial>,
+    /// Only read if the dock has no `strut_partial`.
+    strut: Option<Strut>,
 }
 
 #[derive(Default)]
 struct Screen {
     vec: RefCell<Vec<Dock>>,
+    docks_hidden: bool,
 }
 
 impl Screen {
     pub fn add_dock(&mut self, conn: &Connection, window_id: WindowId) {
         let strut_partial = conn.get_strut_partial(&window_id);
+        let strut = match strut_partial {
+            Some(_) => None,
+            None => conn.get_strut(&window_id),
+        };
         self.vec.borrow_mut().push(Dock {
             window_id,
             strut_partial,
+            strut,
         });
     }
 
+    pub fn contains_dock(&self, window_id: &WindowId) -> bool {
+        self.vec.borrow().iter().any(|d| &d.window_id == window_id)
+    }
+
+    /// Returns the IDs of all docks.
+    pub fn dock_ids(&self) -> Vec<WindowId> {
+        self.vec.borrow().iter().map(|d| d.window_id).collect()
+    }
+
     pub fn remove_dock(&mut self, window_id: &WindowId) {
         self.vec.borrow_mut().retain(|d| &d.window_id != window_id);
     }
 
+    pub fn docks_hidden(&self) -> bool {
+        self.docks_hidden
+    }
+
+    /// Unmaps (or maps) all docks. While hidden, docks don't reserve any space
+    /// on the screen.
+    pub fn set_docks_hidden(&mut self, conn: &Connection, hidden: bool) {
+        self.docks_hidden = hidden;
+        for dock in self.vec.borrow().iter() {
+            if hidden {
+                conn.unmap_window(&dock.window_id);
+            } else {
+                conn.map_window(&dock.window_id);
+            }
+        }
+    }
+
     /// Figure out the usable area of the screen based on the STRUT_PARTIAL of
     /// all docks.
     pub fn viewport(&self, screen_width: u32, screen_height: u32) -> Viewport {
@@ -146,15 +219,25 @@ impl Screen {
             .vec
             .borrow()
             .iter()
-            .filter_map(|o| o.strut_partial.as_ref())
-            .fold((0, 0, 0, 0), |(left, right, top, bottom), s| {
+            .filter(|_| !self.docks_hidden)
+            .filter_map(|o| {
                 // We don't bother looking at the start/end members of the
                 // StrutPartial - treating it more like a Strut.
+                o.strut_partial
+                    .as_ref()
+                    .map(|s| (s.left(), s.right(), s.top(), s.bottom()))
+                    .or_else(|| {
+                        o.strut
+                            .as_ref()
+                            .map(|s| (s.left(), s.right(), s.top(), s.bottom()))
+                    })
+            })
+            .fold((0, 0, 0, 0), |(left, right, top, bottom), (l, r, t, b)| {
                 (
-                    cmp::max(left, s.left()),
-                    cmp::max(right, s.right()),
-                    cmp::max(top, s.top()),
-                    cmp::max(bottom, s.bottom()),
+                    cmp::max(left, l),
+                    cmp::max(right, r),
+                    cmp::max(top, t),
+                    cmp::max(bottom, b),
                 )
             });
         let viewport = Viewport {
@@ -162,61 +245,282 @@ impl Screen {
             y: top,
             width: screen_width - left - right,
             height: screen_height - top - bottom,
+            // We only support a single monitor.
+            monitor: 0,
         };
         debug!("Calculated Viewport as {:?}", viewport);
         viewport
     }
 }
 
+/// The settings changed by zen mode, as they were before it was enabled.
+struct ZenState {
+    group: String,
+    layout: Option<String>,
+    docks_hidden: bool,
+    focus_follows_mouse: bool,
+}
+
+/// The gap between the `toggle_pip()` thumbnail and the edges of the screen.
+#[cfg(feature = "pip")]
+const PIP_MARGIN: u32 = 16;
+
+/// Returns the size of a `toggle_pip()` thumbnail of a window with the given
+/// size: a fifth of the viewport's width (or height, for tall windows),
+/// keeping the window's aspect ratio.
+#[cfg(feature = "pip")]
+fn pip_size(viewport: &Viewport, width: u32, height: u32) -> (u32, u32) {
+    let (width, height) = (u64::from(width.max(1)), u64::from(height.max(1)));
+    let max_width = u64::from(viewport.width / 5).max(1);
+    let max_height = u64::from(viewport.height / 5).max(1);
+    let (width, height) = if width * max_height > height * max_width {
+        (max_width, height * max_width / width)
+    } else {
+        (width * max_height / height, max_height)
+    };
+    (width.max(1) as u32, height.max(1) as u32)
+}
+
+/// How windows are focused with the mouse.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FocusMode {
+    /// Windows are focused when the pointer enters them. This is the default.
+    FollowsMouse,
+    /// Windows are focused when clicked.
+    Click,
+}
+
+/// How often the X server's idle timer is reset while the screen saver is
+/// inhibited. This is well under the shortest timeout anyone is likely to use.
+const IDLE_INHIBIT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The smallest width or height a window can be resized to by dragging.
+const MIN_DRAG_SIZE: u32 = 20;
+
+/// A floating window being moved or resized with the mouse.
+struct Drag {
+    window_id: WindowId,
+    action: MouseAction,
+    /// Where the pointer was when the drag started.
+    pointer: (i16, i16),
+    /// The window's geometry when the drag started.
+    rect: Viewport,
+}
+
+/// Moves `value` by `delta`, stopping at zero.
+fn offset(value: u32, delta: i32) -> u32 {
+    cmp::max(i64::from(value) + i64::from(delta), 0) as u32
+}
+
+/// Set in the environment of the new process by `restart_command()`, so that it puts
+/// the windows it finds back in the groups they were in.
+const RESTARTED_ENV: &str = "LANTA_RESTARTED";
+
+/// Returns the command to run a fresh copy of the lanta binary, with the same
+/// arguments, or `None` if the binary can't be found.
+fn restart_command() -> Option<process::Command> {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(error) => {
+            error!("Could not find the lanta binary to restart: {}", error);
+            return None;
+        }
+    };
+    info!("Re-executing {}", exe.display());
+    let mut command = process::Command::new(exe);
+    command
+        .args(std::env::args_os().skip(1))
+        .env(RESTARTED_ENV, "1");
+    Some(command)
+}
+
 pub struct Lanta {
     connection: Rc<Connection>,
     keys: KeyHandlers,
+    mouse: MouseHandlers,
+    /// Set while a window is being dragged with a mouse binding.
+    drag: Option<Drag>,
+    /// Commands to run once, when `run()` is called.
+    startup: Vec<Command>,
+    /// Whether to re-exec lanta once the event loop exits.
+    restart: bool,
     groups: Stack<Group>,
     screen: Screen,
+    /// Whether the screen saver is kept from activating, see
+    /// `toggle_idle_inhibit()`.
+    idle_inhibited: bool,
+    /// The time of the user's last interaction with the WM (e.g. a key press),
+    /// used to decide whether new windows may steal focus.
+    user_time: Timestamp,
+    /// Pairs of (transient, parent) windows, from WM_TRANSIENT_FOR.
+    transients: Vec<(WindowId, WindowId)>,
+    /// Pairs of (WM_CLASS class, group name) for windows from a restored
+    /// session which we're still waiting to appear.
+    session_routes: Vec<(String, String)>,
+    /// Pairs of (window, group name) for windows which were in a group before
+    /// we restarted, and which we're about to manage again.
+    restart_routes: Vec<(WindowId, String)>,
+    focus_mode: FocusMode,
+    /// Where windows which float without asking for a position are placed.
+    float_placement: FloatPlacement,
+    /// The presets which `apply_preset()` can apply by name.
+    layout_presets: Vec<LayoutPreset>,
+    /// Whether entering a window with the pointer focuses it, when using
+    /// `FocusMode::FollowsMouse`.
+    focus_follows_mouse: bool,
+    /// Whether the pointer is moved to windows focused by commands.
+    warp_pointer: bool,
+    /// Whether windows focused with the pointer (by entering or clicking
+    /// them) are also raised.
+    focus_raises: bool,
+    /// The settings to restore when zen mode is toggled off. `Some` whenever
+    /// zen mode is enabled.
+    zen: Option<ZenState>,
+    /// While set, nothing may change which window is focused.
+    focus_locked: bool,
+    /// Rules from the rules file, placing new windows onto groups.
+    rules: Vec<WindowRule>,
+    borders: Borders,
+    /// The text typed so far when focusing a window by its title. `Some`
+    /// whenever we've grabbed the keyboard to read it.
+    title_search: Option<String>,
+    /// Windows pinned like docks: shown on every group and left out of
+    /// layouts, but without reserving any space.
+    pinned: Vec<WindowId>,
+    /// WM_CLASS instance or class names of windows which we map but otherwise
+    /// leave alone.
+    ignored_classes: Vec<String>,
+    /// Windows we've mapped but are leaving alone, because of
+    /// `ignored_classes`.
+    ignored: Vec<WindowId>,
+    /// Windows which have set the urgency hint in their WM_HINTS, in the
+    /// order they set it.
+    urgent: Vec<WindowId>,
+    /// The thumbnail shown by `toggle_pip()`, if any.
+    #[cfg(feature = "pip")]
+    pip: Option<x::Thumbnail>,
 }
 
 impl Lanta {
-    pub fn new<K>(keys: K, groups: Vec<GroupBuilder>, layouts: &[Box<dyn Layout>]) -> Result<Self>
+    pub fn new<K>(
+        keys: K,
+        groups: Vec<GroupBuilder>,
+        layouts: &[Box<dyn Layout>],
+        borders: Borders,
+    ) -> Result<Self>
     where
         K: Into<KeyHandlers>,
     {
         let keys = keys.into();
         let connection = Rc::new(Connection::connect()?);
         connection.install_as_wm(&keys)?;
+        connection.set_border_width(borders.width);
 
         let groups = Stack::from(
             groups
                 .into_iter()
-                .map(|group: GroupBuilder| group.build(connection.clone(), layouts.to_owned()))
+                .map(|group: GroupBuilder| {
+                    group.build(connection.clone(), layouts.to_owned(), borders)
+                })
                 .collect::<Vec<Group>>(),
         );
 
         let mut wm = Lanta {
             keys,
+            mouse: MouseHandlers::default(),
+            drag: None,
+            startup: Vec::new(),
+            restart: false,
             groups,
             connection: connection.clone(),
             screen: Screen::default(),
+            idle_inhibited: false,
+            user_time: 0,
+            transients: Vec::new(),
+            session_routes: Vec::new(),
+            restart_routes: Vec::new(),
+            focus_mode: FocusMode::FollowsMouse,
+            float_placement: FloatPlacement::Center,
+            layout_presets: Vec::new(),
+            focus_follows_mouse: true,
+            warp_pointer: false,
+            focus_raises: false,
+            zen: None,
+            focus_locked: false,
+            rules: Vec::new(),
+            borders,
+            title_search: None,
+            pinned: Vec::new(),
+            ignored_classes: Vec::new(),
+            ignored: Vec::new(),
+            urgent: Vec::new(),
+            #[cfg(feature = "pip")]
+            pip: None,
         };
 
+        // A broken rules file shouldn't stop us starting, as there'd be no
+        // way to fix it.
+        match wm.load_rules() {
+            Ok(rules) => wm.rules = rules,
+            Err(error) => error!("Could not load rules: {}", error),
+        }
+
         // Learn about existing top-level windows.
         let existing_windows = connection.top_level_windows()?;
+        if std::env::var_os(RESTARTED_ENV).is_some() {
+            // Don't pass the variable on to anything we spawn.
+            std::env::remove_var(RESTARTED_ENV);
+            wm.prepare_restart_routes(&existing_windows);
+        }
         for window in existing_windows {
             wm.manage_window(window);
         }
+        wm.restart_routes.clear();
         let viewport = wm.viewport();
         wm.group_mut().activate(viewport);
         wm.connection.update_ewmh_desktops(&wm.groups);
+        wm.update_client_list();
 
         Ok(wm)
     }
 
     fn viewport(&self) -> Viewport {
+        // The root window is never destroyed, so this only fails if we've
+        // lost the connection, which the event loop will notice.
         let (width, height) = self
             .connection
-            .get_window_geometry(self.connection.root_window_id());
+            .get_window_geometry(self.connection.root_window_id())
+            .unwrap_or_default();
         self.screen.viewport(width, height)
     }
 
+    /// Recalculates the viewport and gives it to every group, laying out the
+    /// active group again.
+    ///
+    /// Call this after anything which may change the usable area of the
+    /// screen or how windows should be arranged. It's safe to call at any
+    /// time, as inactive groups are only laid out once they're activated.
+    pub fn relayout_all(&mut self) {
+        let viewport = self.viewport();
+        let docks = self.screen.dock_ids();
+        for group in self.groups.iter_mut() {
+            group.set_docks(docks.clone());
+            group.update_viewport(viewport);
+        }
+    }
+
+    /// Logs details of the X environment (rather than of the windows we're
+    /// managing), for bug reports and checking setups.
+    pub fn log_diagnostics(&self) {
+        self.connection.log_diagnostics();
+        // We only support a single screen, so there's one viewport.
+        info!(
+            "Viewport: {:?} (docks hidden: {})",
+            self.viewport(),
+            self.screen.docks_hidden()
+        );
+    }
+
     pub fn group(&self) -> &Group {
         self.groups.focused().expect("Invariant: No active group!")
     }
@@ -227,6 +531,18 @@ impl Lanta {
             .expect("Invariant: No active group!")
     }
 
+    /// Returns the title of the active group's focused window, or `None` if
+    /// there is no focused window or it has no title we can decode.
+    pub fn focused_window_title(&self) -> Option<String> {
+        let window_id = self.group().focused_window()?;
+        self.connection.get_window_title(window_id)
+    }
+
+    /// Returns a summary of each group, in order.
+    pub fn group_summaries(&self) -> Vec<GroupSummary> {
+        self.groups.iter().map(|group| group.summary()).collect()
+    }
+
     pub fn switch_group<'a, S>(&'a mut self, name: S)
     where
         S: Into<&'a str>,
@@ -238,48 +554,724 @@ impl Lanta {
             return;
         }
 
+        // Deactivating the group stops it showing the desktop.
+        if self.group().is_showing_desktop() {
+            self.connection.set_showing_desktop(false);
+        }
+
         self.group_mut().deactivate();
         self.groups.focus(|group| group.name() == name);
         let viewport = self.viewport();
         self.group_mut().activate(viewport);
+        self.raise_pinned();
         self.connection.update_ewmh_desktops(&self.groups);
     }
 
+    /// Switches to the group at `index` in the list of groups, doing nothing if
+    /// there is no such group.
+    pub fn switch_group_index(&mut self, index: usize) {
+        let name = match self.groups.iter().nth(index) {
+            Some(group) => group.name().to_owned(),
+            None => {
+                warn!("No group at index {}", index);
+                return;
+            }
+        };
+        self.switch_group(name.as_str());
+    }
+
+    /// Switches every group to the named layout.
+    ///
+    /// Groups without a layout of that name are left unchanged.
+    pub fn set_layout_all(&mut self, name: &str) {
+        for group in self.groups.iter_mut() {
+            if !group.set_layout(name) {
+                warn!("Group {} has no layout named {}", group.name(), name);
+            }
+        }
+    }
+
+    /// Shows the desktop by unmapping all windows in the active group, or
+    /// restores them.
+    pub fn set_showing_desktop(&mut self, showing: bool) {
+        self.group_mut().set_showing_desktop(showing);
+        self.connection.set_showing_desktop(showing);
+    }
+
+    /// Toggles between showing the desktop and the active group's windows.
+    pub fn toggle_show_desktop(&mut self) {
+        let showing = !self.group().is_showing_desktop();
+        self.set_showing_desktop(showing);
+    }
+
     /// Move the focused window from the active group to another named group.
     ///
-    /// If the other named group does not exist, then the window is
-    /// (unfortunately) lost.
-    pub fn move_focused_to_group<'a, S>(&'a mut self, name: S)
+    /// If the other named group does not exist, then the window is left where
+    /// it is and an error is returned.
+    pub fn move_focused_to_group<'a, S>(&'a mut self, name: S) -> Result<()>
     where
         S: Into<&'a str>,
     {
         let name = name.into();
+        match self.group().focused_window().copied() {
+            Some(window_id) => self.move_window_to_group(&window_id, name),
+            None => Ok(()),
+        }
+    }
 
-        // If the group is currently active, then do nothing. This avoids flicker as we
-        // unmap/remap.
-        if name == self.group().name() {
+    /// Returns the name of the group after (or before) the active group,
+    /// wrapping around, or `None` if there's only one group.
+    fn adjacent_group_name(&self, forward: bool) -> Option<String> {
+        let count = self.groups.len();
+        if count < 2 {
+            return None;
+        }
+        let index = self
+            .groups
+            .iter()
+            .position(|group| group.name() == self.group().name())?;
+        let index = if forward {
+            (index + 1) % count
+        } else {
+            (index + count - 1) % count
+        };
+        self.groups
+            .iter()
+            .nth(index)
+            .map(|group| group.name().to_owned())
+    }
+
+    /// Moves the focused window to the next (or previous) group, wrapping
+    /// around. If `follow` is set, then switches to that group too.
+    ///
+    /// Does nothing if there's only one group.
+    pub fn send_focused_to_adjacent_group(&mut self, forward: bool, follow: bool) -> Result<()> {
+        let name = match self.adjacent_group_name(forward) {
+            Some(name) => name,
+            None => return Ok(()),
+        };
+        self.move_focused_to_group(name.as_str())?;
+        if follow {
+            self.switch_group(name.as_str());
+        }
+        Ok(())
+    }
+
+    /// Move a window from whichever group contains it to another named group.
+    ///
+    /// If the window isn't managed, or the other named group does not exist,
+    /// then the window is left where it is and an error is returned.
+    pub fn move_window_to_group(&mut self, window_id: &WindowId, name: &str) -> Result<()> {
+        let source = match self.groups.iter().find(|group| group.contains(window_id)) {
+            Some(group) => group.name().to_owned(),
+            None => return Err(format_err!("Window isn't managed: {}", window_id)),
+        };
+
+        // If the window is already in the group, then do nothing. This avoids
+        // flicker as we unmap/remap.
+        if name == source {
+            return Ok(());
+        }
+
+        // Check the new group exists before taking the window out of its
+        // group, so that it isn't lost.
+        if !self.groups.iter().any(|group| group.name() == name) {
+            return Err(format_err!("No such group: {}", name));
+        }
+
+        let removed = self
+            .groups
+            .iter_mut()
+            .find(|group| group.name() == source)
+            .map(|group| group.remove_window(window_id));
+        // Unlike `Group::remove_focused()`, `Group::remove_window()` leaves the
+        // window mapped, as it's usually used for windows which have gone.
+        self.connection.disable_window_tracking(window_id);
+        self.connection.unmap_window(window_id);
+        self.connection.enable_window_tracking(window_id);
+        let new_group = self.groups.iter_mut().find(|group| group.name() == name);
+        if let (Some(removed), Some(new_group)) = (removed, new_group) {
+            new_group.add_window(removed);
+        }
+        // Only run the source group's on_empty command once the window is in
+        // its new group, so that the command never runs while the window
+        // belongs to no group.
+        self.run_on_empty(&source);
+        Ok(())
+    }
+
+    pub fn is_focus_locked(&self) -> bool {
+        self.focus_locked
+    }
+
+    /// Toggles the focus lock, which stops the pointer, focus commands and
+    /// new windows from changing which window is focused.
+    pub fn toggle_focus_lock(&mut self) {
+        self.focus_locked = !self.focus_locked;
+        info!("Setting focus lock: {}", self.focus_locked);
+    }
+
+    /// Focuses a window, first switching to the group that contains it.
+    pub fn focus_window(&mut self, window_id: &WindowId) {
+        if self.focus_locked {
+            return;
+        }
+        let name = match self.groups.iter().find(|group| group.contains(window_id)) {
+            Some(group) => group.name().to_owned(),
+            None => {
+                error!("Asked to focus window that isn't managed: {}", window_id);
+                return;
+            }
+        };
+        self.switch_group(name.as_str());
+        self.group_mut().focus(window_id);
+        self.warp_pointer_to_focused();
+    }
+
+    /// Sets whether the pointer is moved to the center of windows focused by
+    /// commands (e.g. `cmd::lazy::focus_next()`), so that the pointer doesn't
+    /// get left behind over another window.
+    pub fn set_warp_pointer(&mut self, enabled: bool) {
+        info!("Setting warp pointer: {}", enabled);
+        self.warp_pointer = enabled;
+    }
+
+    /// Moves the pointer to the focused window, if enabled by
+    /// `set_warp_pointer()`.
+    ///
+    /// Call this after changing the focus from the keyboard, but not after
+    /// focusing a window that the pointer has entered.
+    pub fn warp_pointer_to_focused(&self) {
+        if !self.warp_pointer {
             return;
         }
+        if let Some(window_id) = self.group().focused_window() {
+            self.connection.warp_pointer_to_window(window_id);
+        }
+    }
 
-        if let Some(removed) = self.group_mut().remove_focused() {
-            let new_group = self.groups.iter_mut().find(|group| group.name() == name);
-            match new_group {
-                Some(new_group) => {
-                    new_group.add_window(removed);
+    /// Focuses the window that the focused window is transient for (e.g. the
+    /// main window of a dialog).
+    pub fn focus_parent(&mut self) {
+        if self.focus_locked {
+            return;
+        }
+        let parent = self.group().focused_window().and_then(|focused| {
+            self.transients
+                .iter()
+                .find(|(transient, _)| transient == focused)
+                .map(|(_, parent)| *parent)
+        });
+        if let Some(parent) = parent {
+            self.focus_window(&parent);
+        }
+    }
+
+    /// Focuses a window which is transient for the focused window (e.g. a
+    /// dialog it has opened).
+    pub fn focus_child(&mut self) {
+        if self.focus_locked {
+            return;
+        }
+        let child = self.group().focused_window().and_then(|focused| {
+            self.transients
+                .iter()
+                .find(|(_, parent)| parent == focused)
+                .map(|(transient, _)| *transient)
+        });
+        if let Some(child) = child {
+            self.focus_window(&child);
+        }
+    }
+
+    /// Sets where X moves focus when the focused window is destroyed, before
+    /// we focus the next window in the group. See `FocusRevert`.
+    pub fn set_focus_revert(&mut self, focus_revert: FocusRevert) {
+        self.connection.set_focus_revert(focus_revert);
+    }
+
+    /// Move the focused window from the active group to whichever other group
+    /// has the fewest windows.
+    ///
+    /// Ties are broken by group order.
+    pub fn move_focused_to_emptiest_group(&mut self) -> Result<()> {
+        let active = self.group().name().to_owned();
+        let emptiest = self
+            .groups
+            .iter()
+            .filter(|group| group.name() != active)
+            .min_by_key(|group| group.len())
+            .map(|group| group.name().to_owned());
+        match emptiest {
+            Some(name) => self.move_focused_to_group(name.as_str()),
+            None => Ok(()),
+        }
+    }
+
+    /// Toggles whether the X screen saver is inhibited.
+    ///
+    /// While inhibited, the X server's idle timer is reset every 30 seconds,
+    /// so that neither the screen saver nor DPMS blanks the screen (e.g.
+    /// during video playback).
+    pub fn toggle_idle_inhibit(&mut self) {
+        self.idle_inhibited = !self.idle_inhibited;
+        info!("Setting idle inhibit: {}", self.idle_inhibited);
+        if self.idle_inhibited {
+            self.connection.reset_screen_saver();
+            self.connection.set_timer(Some(IDLE_INHIBIT_INTERVAL));
+        } else {
+            self.connection.set_timer(None);
+        }
+    }
+
+    fn on_timer(&self) {
+        if self.idle_inhibited {
+            self.connection.reset_screen_saver();
+        }
+    }
+
+    /// Hides (or shows) any docks, giving their space to the active group.
+    pub fn set_docks_hidden(&mut self, hidden: bool) {
+        info!("Setting docks hidden: {}", hidden);
+        self.screen.set_docks_hidden(&self.connection, hidden);
+        self.relayout_all();
+    }
+
+    pub fn toggle_docks(&mut self) {
+        let hidden = !self.screen.docks_hidden();
+        self.set_docks_hidden(hidden);
+    }
+
+    /// Sets the mouse bindings, which move or resize the window under the
+    /// pointer while dragging with a button held, e.g.
+    /// `vec![(vec![ModKey::Mod4], 1, MouseAction::Move)]`.
+    pub fn set_mouse_bindings<M>(&mut self, bindings: M)
+    where
+        M: Into<MouseHandlers>,
+    {
+        self.mouse = bindings.into();
+        for button in self.mouse.button_combos() {
+            self.connection
+                .grab_button(self.connection.root_window_id(), button);
+        }
+    }
+
+    /// Sets whether windows are focused by entering them with the pointer or
+    /// by clicking them.
+    pub fn set_focus_mode(&mut self, mode: FocusMode) {
+        info!("Setting focus mode: {:?}", mode);
+        self.focus_mode = mode;
+        let windows = self
+            .groups
+            .iter()
+            .flat_map(|group| group.windows())
+            .chain(&self.pinned);
+        for window_id in windows {
+            match mode {
+                FocusMode::Click => self.connection.grab_click(window_id),
+                FocusMode::FollowsMouse => self.connection.ungrab_click(window_id),
+            }
+        }
+    }
+
+    /// Sets the WM_CLASS instance or class names of windows which we map but
+    /// otherwise leave alone, e.g. `vec!["xeyes".to_owned()]`.
+    ///
+    /// Windows of these classes which we're already managing are let go.
+    pub fn set_ignored_classes(&mut self, classes: Vec<String>) {
+        info!("Setting ignored classes: {:?}", classes);
+        self.ignored_classes = classes;
+        let windows: Vec<WindowId> = self
+            .groups
+            .iter()
+            .flat_map(|group| group.windows())
+            .copied()
+            .filter(|window_id| self.is_window_ignored(window_id))
+            .collect();
+        for window_id in windows {
+            info!("Ignoring window: {}", window_id);
+            self.unmanage_window(&window_id);
+            self.connection.map_window(&window_id);
+            self.ignored.push(window_id);
+        }
+    }
+
+    /// Sets where windows which float without asking for a position (e.g.
+    /// dialogs) are placed.
+    pub fn set_float_placement(&mut self, placement: FloatPlacement) {
+        info!("Setting float placement: {:?}", placement);
+        self.float_placement = placement;
+    }
+
+    /// Sets the layout presets which can be applied by name, e.g. with
+    /// `cmd::lazy::apply_preset()`.
+    pub fn set_layout_presets(&mut self, presets: Vec<LayoutPreset>) {
+        info!("Setting {} layout presets", presets.len());
+        self.layout_presets = presets;
+    }
+
+    /// Switches the current group to the named preset's layout, with the
+    /// preset's parameters.
+    pub fn apply_preset(&mut self, name: &str) -> Result<()> {
+        let preset = self
+            .layout_presets
+            .iter()
+            .find(|preset| preset.name() == name)
+            .ok_or_else(|| format_err!("Layout preset doesn't exist: {}", name))?
+            .clone();
+        if !self.group_mut().apply_preset(&preset) {
+            return Err(format_err!(
+                "Layout of preset {} doesn't exist: {}",
+                name,
+                preset.layout()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Applies the preset after the one last applied to the current group, out
+    /// of the named presets, starting from the first.
+    pub fn cycle_presets(&mut self, names: &[&str]) -> Result<()> {
+        let next = match self.group().preset_name() {
+            Some(current) => names
+                .iter()
+                .position(|name| *name == current)
+                .map_or(0, |i| (i + 1) % names.len()),
+            None => 0,
+        };
+        match names.get(next) {
+            Some(name) => self.apply_preset(name),
+            None => Ok(()),
+        }
+    }
+
+    /// Sets whether entering a window with the pointer focuses it, when
+    /// using `FocusMode::FollowsMouse`.
+    pub fn set_focus_follows_mouse(&mut self, enabled: bool) {
+        info!("Setting focus follows mouse: {}", enabled);
+        self.focus_follows_mouse = enabled;
+    }
+
+    /// Toggles whether windows focused by entering them with the pointer are
+    /// also raised, e.g. so that hovering a floating window brings it to the
+    /// front.
+    pub fn toggle_focus_raises(&mut self) {
+        self.focus_raises = !self.focus_raises;
+        info!("Setting focus raises: {}", self.focus_raises);
+    }
+
+    /// Toggles a distraction-free mode, which hides docks, switches the active
+    /// group to the given layout and disables focus-follows-mouse.
+    ///
+    /// Toggling it off restores each of these to how it was before.
+    pub fn toggle_zen(&mut self, layout: &str) {
+        match self.zen.take() {
+            Some(zen) => {
+                info!("Leaving zen mode");
+                self.set_docks_hidden(zen.docks_hidden);
+                self.set_focus_follows_mouse(zen.focus_follows_mouse);
+                if let Some(ref layout) = zen.layout {
+                    if let Some(group) = self.groups.iter_mut().find(|g| g.name() == zen.group) {
+                        group.set_layout(layout);
+                    }
                 }
-                None => {
-                    // It would be nice to put the window back in its group (or avoid taking it out
-                    // of its group until we've checked the new group exists), but it's difficult
-                    // to do this while keeping the borrow checker happy.
-                    error!("Moved window to non-existent group: {}", name);
+            }
+            None => {
+                info!("Entering zen mode");
+                self.zen = Some(ZenState {
+                    group: self.group().name().to_owned(),
+                    layout: self.group().layout_name().map(str::to_owned),
+                    docks_hidden: self.screen.docks_hidden(),
+                    focus_follows_mouse: self.focus_follows_mouse,
+                });
+                self.set_docks_hidden(true);
+                self.set_focus_follows_mouse(false);
+                if !self.group_mut().set_layout(layout) {
+                    warn!("Zen mode layout doesn't exist: {}", layout);
                 }
             }
         }
     }
 
-    /// Returns whether the window is a member of any group.
+    /// Saves the groups, their layouts and their windows as the named session.
+    pub fn save_session(&self, name: &str) -> Result<()> {
+        let groups = self
+            .groups
+            .iter()
+            .map(|group| GroupSession {
+                name: group.name().to_owned(),
+                layout: group.layout_name().unwrap_or_default().to_owned(),
+            })
+            .collect();
+        let windows = self
+            .groups
+            .iter()
+            .flat_map(|group| group.windows().map(move |window_id| (group, window_id)))
+            .filter_map(|(group, window_id)| {
+                let wm_class = self.connection.get_wm_class(window_id)?;
+                Some(WindowSession {
+                    group: group.name().to_owned(),
+                    instance: wm_class.instance,
+                    class: wm_class.class,
+                    title: self
+                        .connection
+                        .get_window_title(window_id)
+                        .unwrap_or_default(),
+                    command: self
+                        .connection
+                        .get_wm_pid(window_id)
+                        .and_then(session::command_line)
+                        .unwrap_or_default(),
+                })
+            })
+            .collect();
+        info!("Saving session: {}", name);
+        Session { groups, windows }.save(name)
+    }
+
+    /// Restores the named session, switching each group to its saved layout
+    /// and re-launching the saved windows.
+    ///
+    /// Programs are launched using the command line they were saved with, or
+    /// the instance part of their WM_CLASS if it couldn't be found. Their
+    /// windows are routed to their saved group by matching the class part of
+    /// WM_CLASS once they appear.
+    pub fn restore_session(&mut self, name: &str) -> Result<()> {
+        let session = Session::load(name)?;
+        info!("Restoring session: {}", name);
+        for saved in &session.groups {
+            match self.groups.iter_mut().find(|g| g.name() == saved.name) {
+                Some(group) => {
+                    if !group.set_layout(&saved.layout) {
+                        warn!(
+                            "Session {} has unknown layout {} for group {}",
+                            name, saved.layout, saved.name
+                        );
+                    }
+                }
+                None => warn!("Session {} has unknown group: {}", name, saved.name),
+            }
+        }
+        for window in session.windows {
+            let (program, args) = window.launch_command();
+            info!(
+                "Launching {} {:?} for group {}",
+                program, args, window.group
+            );
+            if let Err(error) = std::process::Command::new(program).args(args).spawn() {
+                warn!("Could not launch {}: {}", program, error);
+                continue;
+            }
+            self.session_routes.push((window.class, window.group));
+        }
+        Ok(())
+    }
+
+    /// Reloads the rules file, keeping the current rules if it is invalid.
+    pub fn reload_rules(&mut self) -> Result<()> {
+        self.rules = self.load_rules()?;
+        info!("Loaded {} rules", self.rules.len());
+        Ok(())
+    }
+
+    /// Loads the rules file, checking that its rules name existing groups.
+    fn load_rules(&self) -> Result<Vec<WindowRule>> {
+        let groups: Vec<&str> = self.groups.iter().map(|group| group.name()).collect();
+        rules::load(&groups)
+    }
+
+    /// Returns the first rule to match a newly managed window.
+    fn matching_rule(&self, window_id: &WindowId) -> Option<WindowRule> {
+        if self.rules.is_empty() {
+            return None;
+        }
+        let wm_class = self.connection.get_wm_class(window_id);
+        let title = self.connection.get_window_title(window_id);
+        let window_types = self.connection.get_window_types(window_id);
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(wm_class.as_ref(), title.as_deref(), &window_types))
+            .cloned()
+    }
+
+    /// Returns the group a newly managed window should be placed in because
+    /// of a restored session, if any, and forgets the route.
+    fn take_session_route(&mut self, window_id: &WindowId) -> Option<String> {
+        if self.session_routes.is_empty() {
+            return None;
+        }
+        let wm_class = self.connection.get_wm_class(window_id)?;
+        let index = self
+            .session_routes
+            .iter()
+            .position(|(class, _)| *class == wm_class.class)?;
+        Some(self.session_routes.remove(index).1)
+    }
+
+    /// Reads the group each window was in before we restarted from the
+    /// _NET_WM_DESKTOP that `shutdown()` set, and switches back to the group
+    /// that was active.
+    fn prepare_restart_routes(&mut self, windows: &[WindowId]) {
+        let names: Vec<String> = self
+            .groups
+            .iter()
+            .map(|group| group.name().to_owned())
+            .collect();
+        let group_name = |index: u32| names.get(index as usize).cloned();
+        self.restart_routes = windows
+            .iter()
+            .filter_map(|window_id| {
+                let index = self.connection.get_wm_desktop(window_id)?;
+                Some((*window_id, group_name(index)?))
+            })
+            .collect();
+        if let Some(name) = self.connection.get_current_desktop().and_then(group_name) {
+            self.groups.focus(|group| group.name() == name);
+        }
+    }
+
+    /// Returns the group a window was in before we restarted, if any, and
+    /// forgets the route.
+    fn take_restart_route(&mut self, window_id: &WindowId) -> Option<String> {
+        let index = self
+            .restart_routes
+            .iter()
+            .position(|(w, _)| w == window_id)?;
+        Some(self.restart_routes.remove(index).1)
+    }
+
+    /// Returns whether the window is a member of any group, or is otherwise
+    /// known to us (e.g. pinned or ignored).
     fn is_window_managed(&self, window_id: &WindowId) -> bool {
         self.groups.iter().any(|g| g.contains(window_id))
+            || self.pinned.contains(window_id)
+            || self.ignored.contains(window_id)
+    }
+
+    /// Returns whether the window's WM_CLASS is one we've been asked to leave
+    /// alone.
+    fn is_window_ignored(&self, window_id: &WindowId) -> bool {
+        if self.ignored_classes.is_empty() {
+            return false;
+        }
+        self.connection
+            .get_wm_class(window_id)
+            .is_some_and(|wm_class| {
+                self.ignored_classes
+                    .iter()
+                    .any(|class| *class == wm_class.class || *class == wm_class.instance)
+            })
+    }
+
+    /// Pins the focused window so that it stays where it is on every group,
+    /// above other windows, like a dock that doesn't reserve space. If the
+    /// focused window is already pinned, it's unpinned into the active group.
+    pub fn toggle_utility_pin(&mut self) {
+        let input_focus = self.connection.get_input_focus();
+        if let Some(window_id) = input_focus.filter(|w| self.pinned.contains(w)) {
+            info!("Unpinning window: {}", window_id);
+            self.pinned.retain(|w| *w != window_id);
+            self.group_mut().add_window(window_id);
+        } else if let Some(window_id) = self.group().focused_window().copied() {
+            info!("Pinning window: {}", window_id);
+            self.group_mut().remove_window(&window_id);
+            self.connection.restack_window(&window_id, true);
+            self.pinned.push(window_id);
+            let name = self.group().name().to_owned();
+            self.run_on_empty(&name);
+        }
+    }
+
+    /// Keeps pinned windows (and any `toggle_pip()` thumbnail) above the
+    /// active group's windows.
+    fn raise_pinned(&self) {
+        for window_id in &self.pinned {
+            self.connection.restack_window(window_id, true);
+        }
+        #[cfg(feature = "pip")]
+        {
+            if let Some(thumbnail) = &self.pip {
+                self.connection.restack_window(&thumbnail.window(), true);
+            }
+        }
+    }
+
+    /// Shows a thumbnail of the focused window's contents in the bottom right
+    /// corner of the screen, above every other window. If a thumbnail is
+    /// already shown, it's removed instead.
+    ///
+    /// The thumbnail follows changes to the window's contents so long as the
+    /// window is mapped, so it isn't updated while the window's group isn't
+    /// active.
+    #[cfg(feature = "pip")]
+    pub fn toggle_pip(&mut self) -> Result<()> {
+        if let Some(thumbnail) = self.pip.take() {
+            info!("Hiding thumbnail of window: {}", thumbnail.source());
+            self.connection.destroy_thumbnail(thumbnail);
+            return Ok(());
+        }
+
+        let window_id = match self.group().focused_window() {
+            Some(window_id) => *window_id,
+            None => return Ok(()),
+        };
+        let (source_width, source_height) = match self.connection.get_window_rect(&window_id) {
+            Some((_, _, width, height)) => (width, height),
+            None => return Ok(()),
+        };
+        info!("Showing thumbnail of window: {}", window_id);
+        let viewport = self.viewport();
+        let (width, height) = pip_size(&viewport, source_width, source_height);
+        let x = viewport.x + viewport.width.saturating_sub(width + PIP_MARGIN);
+        let y = viewport.y + viewport.height.saturating_sub(height + PIP_MARGIN);
+        let thumbnail = self.connection.create_thumbnail(
+            &window_id,
+            x as i16,
+            y as i16,
+            width as u16,
+            height as u16,
+        )?;
+        self.pip = Some(thumbnail);
+        Ok(())
+    }
+
+    #[cfg(feature = "pip")]
+    fn on_damage_notify(&self, window_id: &WindowId) {
+        if let Some(thumbnail) = self.pip.as_ref().filter(|t| t.source() == *window_id) {
+            self.connection.update_thumbnail(thumbnail);
+        }
+    }
+
+    #[cfg(feature = "pip")]
+    fn on_configure_notify(&mut self, window_id: &WindowId) {
+        let rescaled = match self.pip.as_ref().filter(|t| t.source() == *window_id) {
+            Some(thumbnail) => self.connection.rescale_thumbnail(thumbnail),
+            None => return,
+        };
+        // The window was destroyed before we saw its DestroyNotify.
+        if !rescaled {
+            info!("Hiding thumbnail of destroyed window: {}", window_id);
+            let thumbnail = self.pip.take().unwrap();
+            self.connection.destroy_thumbnail(thumbnail);
+        }
+    }
+
+    /// Manages any mapped top-level windows that we aren't already managing,
+    /// adding them to the active group.
+    ///
+    /// This recovers windows that slipped through without us seeing their
+    /// MapRequest.
+    pub fn rescan_windows(&mut self) -> Result<()> {
+        for window_id in self.connection.top_level_windows()? {
+            if self.is_window_managed(&window_id) || self.screen.contains_dock(&window_id) {
+                continue;
+            }
+            if self.connection.is_window_manageable(&window_id) {
+                info!("Adopting unmanaged window: {}", window_id);
+                self.manage_window(window_id);
+            }
+        }
+        Ok(())
     }
 
     pub fn manage_window(&mut self, window_id: WindowId) {
@@ -296,6 +1288,15 @@ impl Lanta {
             return;
         }
 
+        // Like override-redirect windows, ignored windows are left to place
+        // themselves and aren't in any group.
+        if self.is_window_ignored(&window_id) {
+            info!("Ignoring window: {}", window_id);
+            self.connection.map_window(&window_id);
+            self.ignored.push(window_id);
+            return;
+        }
+
         let window_types = self.connection.get_window_types(&window_id);
         let dock = window_types.contains(&WindowType::Dock);
 
@@ -303,13 +1304,181 @@ impl Lanta {
             .enable_window_key_events(&window_id, &self.keys);
 
         if dock {
-            self.connection.map_window(&window_id);
+            if !self.screen.docks_hidden() {
+                self.connection.map_window(&window_id);
+            }
             self.screen.add_dock(&self.connection, window_id);
-            let viewport = self.viewport();
-            self.group_mut().update_viewport(viewport);
+            self.relayout_all();
         } else {
+            // A new window should be visible, so stop showing the desktop.
+            if self.group().is_showing_desktop() {
+                self.set_showing_desktop(false);
+            }
+            let transient_for = self.connection.get_wm_transient_for(&window_id);
+            if let Some(ref parent) = transient_for {
+                self.transients.push((window_id, *parent));
+            }
             self.connection.enable_window_tracking(&window_id);
-            self.group_mut().add_window(window_id);
+            self.update_urgency(&window_id);
+            if self.focus_mode == FocusMode::Click {
+                self.connection.grab_click(&window_id);
+            }
+            // Toolkits which position themselves (or draw shadows) using
+            // _NET_FRAME_EXTENTS expect it to be set, even without borders.
+            let border = self.borders.width;
+            self.connection.set_window_border_width(&window_id, border);
+            self.connection
+                .set_frame_extents(&window_id, border, border, border, border);
+            let states = self.connection.get_window_states(&window_id);
+            let layer = if states.contains(&WindowState::Above) {
+                Layer::Above
+            } else if states.contains(&WindowState::Below) {
+                Layer::Below
+            } else {
+                Layer::Normal
+            };
+            let rule = self.matching_rule(&window_id);
+            let float_rule = rule.as_ref().is_some_and(|rule| rule.float);
+            let floating = self.initial_floating_geometry(
+                &window_id,
+                &window_types,
+                transient_for,
+                float_rule,
+            );
+            // Windows which ask to start minimized, or which were hidden
+            // before we restarted, are added hidden, and so aren't mapped or
+            // focused.
+            let restart_route = self.take_restart_route(&window_id);
+            let iconic = self.connection.is_window_initially_iconic(&window_id)
+                || (restart_route.is_some()
+                    && self.connection.get_wm_state(&window_id) == Some(WmState::Iconic));
+            let focus = !iconic && self.should_focus_new_window(&window_id);
+            let fullscreen = states.contains(&WindowState::Fullscreen)
+                || rule.as_ref().is_some_and(|rule| rule.fullscreen);
+            let route = restart_route
+                .or_else(|| self.take_session_route(&window_id))
+                .or_else(|| rule.map(|rule| rule.group));
+            let group = match route {
+                Some(ref name) if name != self.group().name() => {
+                    self.groups.iter_mut().find(|g| g.name() == name)
+                }
+                _ => None,
+            };
+            // Windows routed to another group by a restart, a restored session
+            // or a rule are focused within that group, unless they're
+            // minimized. The
+            // focus lock only keeps the active group's focus where it is.
+            let focus = if group.is_some() {
+                !iconic
+            } else {
+                focus && !self.focus_locked
+            };
+            let group = match group {
+                Some(group) => group,
+                None => self.group_mut(),
+            };
+            group.with_suppressed_layout(|group| {
+                if let Some(rect) = floating {
+                    group.set_floating(&window_id, rect);
+                }
+                if focus {
+                    group.add_window(window_id);
+                } else {
+                    group.add_window_unfocused(window_id);
+                }
+                group.set_layer(&window_id, layer);
+                if fullscreen {
+                    group.set_fullscreen(&window_id, true);
+                }
+                if iconic {
+                    group.hide_window(&window_id);
+                }
+            });
+            self.update_client_list();
+        }
+    }
+
+    /// Updates _NET_CLIENT_LIST with the windows in each group, in the order
+    /// of the groups, followed by any pinned windows.
+    fn update_client_list(&self) {
+        let windows: Vec<WindowId> = self
+            .groups
+            .iter()
+            .flat_map(|group| group.windows())
+            .chain(&self.pinned)
+            .copied()
+            .collect();
+        self.connection.update_client_list(&windows);
+    }
+
+    /// Decides whether a new window should float rather than be tiled,
+    /// returning the geometry it should float at.
+    ///
+    /// Transient windows, dialogs, utility windows, splash screens and windows
+    /// matched by a rule with `float=true` float.
+    /// Transient windows are centered over their parent. Other windows stay
+    /// where they asked to be, or are placed by the `FloatPlacement` if they
+    /// didn't ask for a position.
+    fn initial_floating_geometry(
+        &self,
+        window_id: &WindowId,
+        window_types: &[WindowType],
+        transient_for: Option<WindowId>,
+        float_rule: bool,
+    ) -> Option<Viewport> {
+        let floats = float_rule
+            || transient_for.is_some()
+            || window_types.iter().any(|t| {
+                *t == WindowType::Dialog || *t == WindowType::Utility || *t == WindowType::Splash
+            });
+        if !floats {
+            return None;
+        }
+        let (x, y, width, height) = self.connection.get_window_rect(window_id)?;
+        let parent = transient_for.and_then(|parent| self.connection.get_window_rect(&parent));
+        let (x, y) = match parent {
+            Some((parent_x, parent_y, parent_width, parent_height)) => (
+                parent_x + parent_width.saturating_sub(width) / 2,
+                parent_y + parent_height.saturating_sub(height) / 2,
+            ),
+            None if self
+                .connection
+                .get_wm_normal_hints(window_id)
+                .is_some_and(|hints| hints.position) =>
+            {
+                (x, y)
+            }
+            None => {
+                let floats: Vec<Viewport> = self.group().floating_geometries().collect();
+                self.float_placement.place(
+                    &self.viewport(),
+                    (width, height),
+                    self.connection.get_pointer_position(),
+                    &floats,
+                )
+            }
+        };
+        Some(Viewport {
+            x,
+            y,
+            width,
+            height,
+            monitor: self.group().viewport().monitor,
+        })
+    }
+
+    /// Decides whether a newly managed window should be focused, using its
+    /// _NET_WM_USER_TIME to avoid stealing focus.
+    ///
+    /// Windows with a user time of 0 have asked not to be focused. Windows
+    /// whose user time is older than the user's last interaction with us are
+    /// not focused, as the user has since moved on to something else.
+    /// Windows without a user time are always focused.
+    fn should_focus_new_window(&self, window_id: &WindowId) -> bool {
+        match self.connection.get_wm_user_time(window_id) {
+            Some(0) => false,
+            Some(time) => self.user_time == 0 || !x::is_later(self.user_time, time),
+            None => true,
         }
     }
 
@@ -318,31 +1487,191 @@ impl Lanta {
 
         // Remove the window from whichever Group it is in. Special case for
         // docks which aren't in any group.
-        self.groups
+        let group = self
+            .groups
             .iter_mut()
             .find(|group| group.contains(window_id))
-            .map(|group| group.remove_window(window_id));
+            .map(|group| {
+                group.remove_window(window_id);
+                group.name().to_owned()
+            });
         self.screen.remove_dock(window_id);
+        self.pinned.retain(|w| w != window_id);
+        self.ignored.retain(|w| w != window_id);
+        self.urgent.retain(|w| w != window_id);
+        #[cfg(feature = "pip")]
+        {
+            if self.pip.as_ref().is_some_and(|t| t.source() == *window_id) {
+                let thumbnail = self.pip.take().unwrap();
+                self.connection.destroy_thumbnail(thumbnail);
+            }
+        }
+        self.transients
+            .retain(|(transient, parent)| transient != window_id && parent != window_id);
 
         // The viewport may have changed.
-        let viewport = self.viewport();
-        self.group_mut().update_viewport(viewport);
+        self.relayout_all();
+        self.update_client_list();
+
+        if let Some(name) = group {
+            self.run_on_empty(&name);
+        }
+    }
+
+    /// Runs the named group's `on_empty` command if a window has just been
+    /// removed from it and it has none left.
+    fn run_on_empty(&mut self, name: &str) {
+        let command = match self.groups.iter().find(|group| group.name() == name) {
+            Some(group) if group.is_empty() => group.on_empty(),
+            _ => None,
+        };
+        if let Some(command) = command {
+            info!("Group {} is empty, running its on_empty command", name);
+            if let Err(error) = command(self) {
+                error!(
+                    "Error running on_empty command of group {}: {}",
+                    name, error
+                );
+            }
+        }
+    }
+
+    /// Stops the WM once the current command is done, handing all windows
+    /// back unmanaged.
+    pub fn quit(&self) {
+        info!("Quitting");
+        x::request_quit();
+    }
+
+    /// Stops the WM once the current command is done, like `quit()`, and
+    /// then replaces it with a fresh copy of the lanta binary, which picks the
+    /// windows back up in their groups. If that fails, the windows are left
+    /// as `quit()` leaves them.
+    pub fn restart(&mut self) {
+        info!("Restarting");
+        self.restart = true;
+        x::request_quit();
+    }
+
+    /// Hands every window back unmanaged and stops acting as the window
+    /// manager, so that whatever runs next (another window manager, or none)
+    /// finds the windows as they were before we started.
+    fn shutdown(&mut self) {
+        info!("Restoring windows before exiting");
+        #[cfg(feature = "pip")]
+        {
+            if let Some(thumbnail) = self.pip.take() {
+                self.connection.destroy_thumbnail(thumbnail);
+            }
+        }
+
+        // When restarting, record each window's group for the new process,
+        // and leave the windows which aren't showing unmapped, rather than
+        // flashing them up until they're managed again.
+        let active = self.group().name();
+        for (index, group) in self.groups.iter().enumerate() {
+            for window_id in group.windows() {
+                if self.restart {
+                    self.connection.set_wm_desktop(window_id, index as u32);
+                }
+                let showing = group.name() == active && !group.is_hidden(window_id);
+                self.connection
+                    .release_window(window_id, showing || !self.restart);
+            }
+        }
+        let docks = self.screen.dock_ids();
+        for window_id in self.pinned.iter().chain(&docks) {
+            self.connection.release_window(window_id, true);
+        }
+        self.connection.uninstall_as_wm();
+    }
+
+    /// Adds a command to run once the WM has started, before it handles any
+    /// events, e.g. `cmd::lazy::spawn()` to launch a bar.
+    ///
+    /// Commands run in the order they were added.
+    pub fn on_startup(&mut self, command: Command) {
+        self.startup.push(command);
     }
 
     pub fn run(mut self) {
         info!("Started WM, entering event loop.");
+        x::quit_on_signals();
+        for command in std::mem::take(&mut self.startup) {
+            if let Err(error) = command(&mut self) {
+                error!("Error running startup command: {}", error);
+            }
+        }
         let event_loop_connection = self.connection.clone();
         let event_loop = event_loop_connection.get_event_loop();
         for event in event_loop {
             match event {
-                Event::MapRequest(window_id) => self.on_map_request(window_id),
+                Event::MapRequest(window_ids) => self.on_map_requests(window_ids),
                 Event::UnmapNotify(window_id) => self.on_unmap_notify(&window_id),
                 Event::DestroyNotify(window_id) => self.on_destroy_notify(&window_id),
-                Event::KeyPress(key) => self.on_key_press(key),
+                Event::KeyPress(key, time) => self.on_key(key, KeyTrigger::Press, time),
+                Event::KeyRelease(key, time) => self.on_key(key, KeyTrigger::Release, time),
                 Event::EnterNotify(window_id) => self.on_enter_notify(&window_id),
+                Event::PropertyNotify(window_id, atom) => self.on_property_notify(&window_id, atom),
+                Event::ButtonPress(button, window_id, x, y) => {
+                    self.on_button_press(&button, window_id, (x, y))
+                }
+                Event::ButtonRelease => self.drag = None,
+                Event::Click(window_id) => self.on_click(&window_id),
+                Event::MotionNotify(x, y) => self.on_motion_notify((x, y)),
+                Event::ShowDesktopRequest(showing) => self.set_showing_desktop(showing),
+                Event::ConfigureRequest(window_id) => self.on_configure_request(&window_id),
+                Event::StateRequest(window_id, state, action) => {
+                    self.on_state_request(&window_id, state, action)
+                }
+                Event::CurrentDesktopRequest(index) => self.switch_group_index(index),
+                Event::ActivateRequest(window_id) => self.on_activate_request(&window_id),
+                Event::Timer => self.on_timer(),
+                #[cfg(feature = "pip")]
+                Event::DamageNotify(window_id) => self.on_damage_notify(&window_id),
+                #[cfg(feature = "pip")]
+                Event::ConfigureNotify(window_id) => self.on_configure_notify(&window_id),
             }
         }
         info!("Event loop exiting");
+        // Find the binary before releasing any windows, so that if we can't,
+        // we quit normally rather than leaving hidden windows unmapped.
+        let restart = if self.restart {
+            restart_command()
+        } else {
+            None
+        };
+        self.restart = restart.is_some();
+        self.shutdown();
+
+        if let Some(mut command) = restart {
+            // libxcb opens its socket close-on-exec, so the new instance
+            // doesn't have to wait for the X server to notice we've gone.
+            let error = command.exec();
+            error!("Could not restart: {}", error);
+            // We're quitting instead, so map the windows we left unmapped for
+            // the new instance, as a normal quit does.
+            self.restart = false;
+            self.shutdown();
+        }
+    }
+
+    fn on_map_requests(&mut self, window_ids: Vec<WindowId>) {
+        if window_ids.len() == 1 {
+            return self.on_map_request(window_ids.into_iter().next().unwrap());
+        }
+
+        // Lay out once for the whole burst, rather than once per window.
+        debug!("Handling {} MapRequests at once", window_ids.len());
+        for group in self.groups.iter_mut() {
+            group.suppress_layout();
+        }
+        for window_id in window_ids {
+            self.on_map_request(window_id);
+        }
+        for group in self.groups.iter_mut() {
+            group.resume_layout();
+        }
     }
 
     fn on_map_request(&mut self, window_id: WindowId) {
@@ -351,6 +1680,8 @@ impl Lanta {
             // (This will have the side-effect of mapping the window, as new windows are focused
             // and focused windows are mapped).
             self.manage_window(window_id);
+        } else if self.ignored.contains(&window_id) {
+            self.connection.map_window(&window_id);
         } else if self.group().contains(&window_id) {
             // Otherwise, if the window is in the active group, focus it. The application probably
             // wants us to make it prominent. Log as there may be misbehaving applications that
@@ -374,15 +1705,265 @@ impl Lanta {
         self.unmanage_window(window_id);
     }
 
-    fn on_key_press(&mut self, key: KeyCombo) {
-        if let Some(handler) = self.keys.get(&key) {
+    /// Grabs the keyboard to read part of a window title, then focuses the
+    /// first window whose title contains it.
+    ///
+    /// Enter focuses the window and Escape cancels.
+    pub fn start_focus_by_title(&mut self) -> Result<()> {
+        self.connection.grab_keyboard()?;
+        info!("Reading window title to focus");
+        self.title_search = Some(String::new());
+        Ok(())
+    }
+
+    /// Returns the first managed window whose title contains `text`, ignoring
+    /// case.
+    fn find_window_by_title(&self, text: &str) -> Option<WindowId> {
+        let text = text.to_lowercase();
+        self.groups
+            .iter()
+            .flat_map(|group| group.windows())
+            .find(|window_id| {
+                self.connection
+                    .get_window_title(window_id)
+                    .is_some_and(|title| title.to_lowercase().contains(&text))
+            })
+            .copied()
+    }
+
+    fn on_title_search_key(&mut self, key: &KeyCombo) {
+        let search = match self.title_search {
+            Some(ref mut search) => search,
+            None => return,
+        };
+        match key.keysym {
+            keysym::XK_Escape => {
+                info!("Cancelled focusing window by title");
+            }
+            keysym::XK_Return => {
+                let search = search.clone();
+                match self.find_window_by_title(&search) {
+                    Some(window_id) => self.focus_window(&window_id),
+                    None => info!("No window with title containing: {}", search),
+                }
+            }
+            keysym::XK_BackSpace => {
+                search.pop();
+                return;
+            }
+            // Printable Latin-1 keysyms are the same as their characters.
+            keysym @ 0x20..=0x7e => {
+                search.push(keysym as u8 as char);
+                debug!("Focusing window by title: {}", search);
+                return;
+            }
+            _ => return,
+        }
+        self.title_search = None;
+        self.connection.ungrab_keyboard();
+    }
+
+    fn on_key(&mut self, key: KeyCombo, trigger: KeyTrigger, time: Timestamp) {
+        self.user_time = time;
+        if self.title_search.is_some() {
+            if trigger == KeyTrigger::Press {
+                self.on_title_search_key(&key);
+            }
+            return;
+        }
+        if let Some(handler) = self.keys.get(&key, trigger) {
             if let Err(error) = (handler)(self) {
                 error!("Error running command for key command {:?}: {}", key, error);
             }
         }
     }
 
+    fn on_configure_request(&mut self, window_id: &WindowId) {
+        if let Some(group) = self
+            .groups
+            .iter_mut()
+            .find(|group| group.contains(window_id))
+        {
+            group.update_floating_geometry(window_id);
+        }
+    }
+
+    /// Starts dragging the window under the pointer, if the button is bound
+    /// to a `MouseAction`. Tiled windows are made to float at their current
+    /// geometry first.
+    fn on_button_press(&mut self, button: &ButtonCombo, window_id: WindowId, pointer: (i16, i16)) {
+        let action = match self.mouse.get(button) {
+            Some(action) => action,
+            None => return,
+        };
+        // Docks, pinned and ignored windows aren't in any group, and are left
+        // alone.
+        if !self.group().contains(&window_id) {
+            return;
+        }
+        let rect = match self.group().floating_geometry(&window_id) {
+            Some(rect) => rect,
+            None => {
+                let (x, y, width, height) = match self.connection.get_window_rect(&window_id) {
+                    Some(rect) => rect,
+                    None => return,
+                };
+                let rect = Viewport {
+                    x,
+                    y,
+                    width,
+                    height,
+                    ..self.viewport()
+                };
+                self.group_mut().set_floating(&window_id, rect);
+                rect
+            }
+        };
+        debug!("Starting {:?} of window: {}", action, window_id);
+        self.group_mut().focus(&window_id);
+        self.drag = Some(Drag {
+            window_id,
+            action,
+            pointer,
+            rect,
+        });
+    }
+
+    fn on_motion_notify(&mut self, pointer: (i16, i16)) {
+        let drag = match self.drag {
+            Some(ref drag) => drag,
+            None => return,
+        };
+        let dx = i32::from(pointer.0) - i32::from(drag.pointer.0);
+        let dy = i32::from(pointer.1) - i32::from(drag.pointer.1);
+        let rect = match drag.action {
+            MouseAction::Move => Viewport {
+                x: offset(drag.rect.x, dx),
+                y: offset(drag.rect.y, dy),
+                ..drag.rect
+            },
+            MouseAction::Resize => Viewport {
+                width: cmp::max(offset(drag.rect.width, dx), MIN_DRAG_SIZE),
+                height: cmp::max(offset(drag.rect.height, dy), MIN_DRAG_SIZE),
+                ..drag.rect
+            },
+        };
+        let window_id = drag.window_id;
+        self.group_mut().move_floating(&window_id, rect);
+    }
+
+    fn on_activate_request(&mut self, window_id: &WindowId) {
+        // Docks, pinned and ignored windows aren't in any group, and are left
+        // alone.
+        if self.groups.iter().any(|group| group.contains(window_id)) {
+            self.focus_window(window_id);
+        } else {
+            debug!("Ignoring activation of window in no group: {}", window_id);
+        }
+    }
+
+    fn on_state_request(&mut self, window_id: &WindowId, state: WindowState, action: StateAction) {
+        let group = match self
+            .groups
+            .iter_mut()
+            .find(|group| group.contains(window_id))
+        {
+            Some(group) => group,
+            None => return,
+        };
+        match state {
+            WindowState::DemandsAttention => {
+                let demands = action.apply(group.demands_attention(window_id));
+                group.set_demands_attention(window_id, demands);
+            }
+            WindowState::Fullscreen => {
+                let fullscreen = action.apply(group.is_fullscreen(window_id));
+                group.set_fullscreen(window_id, fullscreen);
+            }
+            WindowState::Above | WindowState::Below => {
+                let layer = if state == WindowState::Above {
+                    Layer::Above
+                } else {
+                    Layer::Below
+                };
+                let current = group.layer(window_id) == layer;
+                let layer = if action.apply(current) {
+                    layer
+                } else if current {
+                    Layer::Normal
+                } else {
+                    // Removing a layer the window isn't in.
+                    return;
+                };
+                group.set_layer(window_id, layer);
+            }
+            _ => debug!(
+                "Ignoring request to change state {:?} of window {}",
+                state, window_id
+            ),
+        }
+    }
+
     fn on_enter_notify(&mut self, window_id: &WindowId) {
+        if self.focus_mode == FocusMode::FollowsMouse && self.focus_follows_mouse {
+            self.focus_with_pointer(window_id);
+        }
+    }
+
+    fn on_property_notify(&mut self, window_id: &WindowId, atom: Atom) {
+        if atom == x::WM_HINTS && self.is_window_managed(window_id) {
+            self.update_urgency(window_id);
+        }
+    }
+
+    /// Re-reads whether the window has set the urgency hint.
+    fn update_urgency(&mut self, window_id: &WindowId) {
+        let urgent = self.connection.is_urgent(window_id);
+        if urgent == self.urgent.contains(window_id) {
+            return;
+        }
+        info!("Window is urgent: {}: {}", window_id, urgent);
+        if urgent {
+            self.urgent.push(*window_id);
+        } else {
+            self.urgent.retain(|w| w != window_id);
+        }
+    }
+
+    /// Focuses the window which has been urgent for longest, switching to the
+    /// group which contains it.
+    pub fn focus_urgent(&mut self) {
+        let urgent = self
+            .urgent
+            .iter()
+            .find(|window_id| self.groups.iter().any(|group| group.contains(window_id)))
+            .copied();
+        match urgent {
+            Some(window_id) => self.focus_window(&window_id),
+            None => debug!("No urgent windows to focus"),
+        }
+    }
+
+    fn on_click(&mut self, window_id: &WindowId) {
+        self.focus_with_pointer(window_id);
+        self.connection.replay_click();
+    }
+
+    /// Focuses a window that the pointer has entered or clicked.
+    fn focus_with_pointer(&mut self, window_id: &WindowId) {
+        if self.focus_locked {
+            return;
+        }
+        // Pinned windows aren't in any group, so focus them directly.
+        if self.pinned.contains(window_id) {
+            self.connection.focus_window(window_id);
+            return;
+        }
         self.group_mut().focus(window_id);
+        if self.focus_raises && self.group().contains(window_id) {
+            self.connection.restack_window(window_id, true);
+            self.group().raise_docks();
+            self.raise_pinned();
+        }
     }
 }
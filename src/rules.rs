@@ -0,0 +1,278 @@
+//! Rules, read from `$XDG_CONFIG_HOME/lanta/rules`, which place newly managed
+//! windows onto a specific group, optionally floating or fullscreen.
+//!
+//! Each non-empty line which doesn't start with `#` is a rule made up of
+//! whitespace-separated `key=value` fields. Values containing spaces can be
+//! wrapped in double quotes. A rule must have a `group` and at least one of:
+//!
+//! - `class`: matches either part of the window's WM_CLASS exactly.
+//! - `title`: matches if the window's title contains the value.
+//! - `type`: matches the window's _NET_WM_WINDOW_TYPE, e.g. `dialog`.
+//!
+//! The group must be one of the configured groups. A rule can also have
+//! `float=true` or `fullscreen=true` to start matching windows floating or
+//! fullscreen.
+//!
+//! For example: `class=Firefox group=web` or `title="- Slack" group=chat` or
+//! `class=mpv group=media fullscreen=true`.
+//!
+//! The first rule to match a window decides its group and flags.
+
+use std::fs;
+
+use failure::{format_err, ResultExt};
+
+use crate::x::{WindowType, WmClass};
+use crate::Result;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindowRule {
+    class: Option<String>,
+    title: Option<String>,
+    window_type: Option<WindowType>,
+    pub group: String,
+    pub float: bool,
+    pub fullscreen: bool,
+}
+
+impl WindowRule {
+    pub fn matches(
+        &self,
+        wm_class: Option<&WmClass>,
+        title: Option<&str>,
+        window_types: &[WindowType],
+    ) -> bool {
+        let class_matches = self.class.as_ref().is_none_or(|class| {
+            wm_class.is_some_and(|c| c.class == *class || c.instance == *class)
+        });
+        let title_matches = self
+            .title
+            .as_ref()
+            .is_none_or(|t| title.is_some_and(|title| title.contains(t.as_str())));
+        let type_matches = self
+            .window_type
+            .is_none_or(|window_type| window_types.contains(&window_type));
+        class_matches && title_matches && type_matches
+    }
+}
+
+/// Loads the rules file, returning no rules if it doesn't exist.
+///
+/// `groups` are the names of the groups which rules may place windows in.
+pub fn load(groups: &[&str]) -> Result<Vec<WindowRule>> {
+    let xdg_dirs = xdg::BaseDirectories::with_prefix("lanta")?;
+    let path = match xdg_dirs.find_config_file("rules") {
+        Some(path) => path,
+        None => return Ok(Vec::new()),
+    };
+    let contents = fs::read_to_string(&path)
+        .with_context(|_| format!("Could not read rules: {}", path.display()))?;
+    parse(&contents, groups)
+        .map_err(|error| format_err!("Invalid rules in {}: {}", path.display(), error))
+}
+
+fn window_type(name: &str) -> Option<WindowType> {
+    let window_type = match name {
+        "desktop" => WindowType::Desktop,
+        "dock" => WindowType::Dock,
+        "toolbar" => WindowType::Toolbar,
+        "menu" => WindowType::Menu,
+        "utility" => WindowType::Utility,
+        "splash" => WindowType::Splash,
+        "dialog" => WindowType::Dialog,
+        "dropdown_menu" => WindowType::DropdownMenu,
+        "popup_menu" => WindowType::PopupMenu,
+        "tooltip" => WindowType::Tooltip,
+        "notification" => WindowType::Notification,
+        "combo" => WindowType::Combo,
+        "dnd" => WindowType::Dnd,
+        "normal" => WindowType::Normal,
+        _ => return None,
+    };
+    Some(window_type)
+}
+
+fn flag(key: &str, value: &str) -> std::result::Result<bool, String> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(format!("{} must be true or false: {}", key, value)),
+    }
+}
+
+/// Splits a line into its `key=value` fields, honouring double quotes.
+fn fields(line: &str) -> std::result::Result<Vec<(String, String)>, String> {
+    let mut fields = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            return Ok(fields);
+        }
+
+        let mut key = String::new();
+        loop {
+            match chars.next() {
+                Some('=') if !key.is_empty() => break,
+                Some(c) if !c.is_whitespace() && c != '=' => key.push(c),
+                _ => return Err(format!("expected key=value, found {:?}", key)),
+            }
+        }
+
+        let mut value = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some(c) => value.push(c),
+                    None => return Err(format!("unterminated quote in {}", key)),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                value.push(c);
+                chars.next();
+            }
+        }
+        fields.push((key, value));
+    }
+}
+
+fn parse_rule(line: &str, groups: &[&str]) -> std::result::Result<WindowRule, String> {
+    let mut rule = WindowRule {
+        class: None,
+        title: None,
+        window_type: None,
+        group: String::new(),
+        float: false,
+        fullscreen: false,
+    };
+    for (key, value) in fields(line)? {
+        match key.as_str() {
+            "class" => rule.class = Some(value),
+            "title" => rule.title = Some(value),
+            "type" => {
+                rule.window_type =
+                    Some(window_type(&value).ok_or_else(|| format!("unknown type: {}", value))?)
+            }
+            "group" if !groups.contains(&value.as_str()) => {
+                return Err(format!("unknown group: {}", value))
+            }
+            "group" => rule.group = value,
+            "float" => rule.float = flag(&key, &value)?,
+            "fullscreen" => rule.fullscreen = flag(&key, &value)?,
+            _ => return Err(format!("unknown key: {}", key)),
+        }
+    }
+    if rule.group.is_empty() {
+        return Err("missing group".to_owned());
+    }
+    if rule.class.is_none() && rule.title.is_none() && rule.window_type.is_none() {
+        return Err("rule matches every window".to_owned());
+    }
+    Ok(rule)
+}
+
+fn parse(contents: &str, groups: &[&str]) -> Result<Vec<WindowRule>> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| {
+            parse_rule(line, groups).map_err(|error| format_err!("line {}: {}", i + 1, error))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const GROUPS: &[&str] = &["web", "chat", "media"];
+
+    fn wm_class(instance: &str, class: &str) -> WmClass {
+        WmClass {
+            instance: instance.to_owned(),
+            class: class.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let rules = parse(
+            "# Comment\n\nclass=Firefox group=web\ntitle=\"- Slack\" type=normal group=chat\n",
+            GROUPS,
+        )
+        .unwrap();
+        assert_eq!(
+            rules,
+            vec![
+                WindowRule {
+                    class: Some("Firefox".to_owned()),
+                    title: None,
+                    window_type: None,
+                    group: "web".to_owned(),
+                    float: false,
+                    fullscreen: false,
+                },
+                WindowRule {
+                    class: None,
+                    title: Some("- Slack".to_owned()),
+                    window_type: Some(WindowType::Normal),
+                    group: "chat".to_owned(),
+                    float: false,
+                    fullscreen: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_reports_line() {
+        let error = parse("class=Firefox group=web\n\nclass=Slack\n", GROUPS).unwrap_err();
+        assert_eq!(error.to_string(), "line 3: missing group");
+        let error = parse("class=Firefox group=web\nclass=Slack group=work\n", GROUPS).unwrap_err();
+        assert_eq!(error.to_string(), "line 2: unknown group: work");
+    }
+
+    #[test]
+    fn test_parse_flags() {
+        let rule = parse_rule("class=mpv group=media fullscreen=true", GROUPS).unwrap();
+        assert!(rule.fullscreen);
+        assert!(!rule.float);
+        let rule =
+            parse_rule("class=Gimp group=media float=true fullscreen=false", GROUPS).unwrap();
+        assert!(rule.float);
+        assert!(!rule.fullscreen);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(parse("class=Firefox group=web float=yes", GROUPS).is_err());
+        assert!(parse("class=Firefox group=web sticky=true", GROUPS).is_err());
+        assert!(parse("type=window group=web", GROUPS).is_err());
+        assert!(parse("title=\"unterminated group=web", GROUPS).is_err());
+        assert!(parse("group=web", GROUPS).is_err());
+        assert!(parse("class group=web", GROUPS).is_err());
+    }
+
+    #[test]
+    fn test_matches() {
+        let rule = parse_rule("class=Firefox type=normal group=web", GROUPS).unwrap();
+        let firefox = wm_class("Navigator", "Firefox");
+        assert!(rule.matches(Some(&firefox), None, &[WindowType::Normal]));
+        assert!(!rule.matches(Some(&firefox), None, &[WindowType::Dialog]));
+        assert!(!rule.matches(None, None, &[WindowType::Normal]));
+
+        let rule = parse_rule("title=Slack group=chat", GROUPS).unwrap();
+        assert!(rule.matches(None, Some("Slack - General"), &[]));
+        assert!(!rule.matches(None, Some("Firefox"), &[]));
+    }
+}
@@ -0,0 +1,214 @@
+//! Snapshots of the groups and their windows, which can be saved to disk and
+//! later used to restore a workspace.
+//!
+//! Sessions are stored as plain text under `$XDG_DATA_HOME/lanta/sessions/`,
+//! one record per line with tab-separated fields. Lines are either
+//! `group <name> <layout>` or `window <group> <instance> <class> <title>
+//! <command>...`, where the command line may take any number of fields.
+
+use std::fs;
+use std::path::PathBuf;
+
+use failure::{format_err, ResultExt};
+
+use crate::Result;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GroupSession {
+    pub name: String,
+    pub layout: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindowSession {
+    pub group: String,
+    /// The instance part of the window's WM_CLASS. This is usually the name of
+    /// the program, so is also used to re-launch it.
+    pub instance: String,
+    pub class: String,
+    pub title: String,
+    /// The command line of the process owning the window, if it could be
+    /// found. This is used in preference to `instance` to re-launch it.
+    pub command: Vec<String>,
+}
+
+impl WindowSession {
+    /// Returns the program and arguments to re-launch the window with.
+    pub fn launch_command(&self) -> (&str, &[String]) {
+        match self.command.split_first() {
+            Some((program, args)) => (program, args),
+            None => (&self.instance, &[]),
+        }
+    }
+}
+
+/// Reads the command line of the process with the given ID from `/proc`.
+pub fn command_line(pid: u32) -> Option<Vec<String>> {
+    let contents = fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+    let command = parse_command_line(&contents);
+    if command.is_empty() {
+        None
+    } else {
+        Some(command)
+    }
+}
+
+/// Splits the NUL-separated arguments of `/proc/<pid>/cmdline`.
+fn parse_command_line(contents: &[u8]) -> Vec<String> {
+    contents
+        .split(|&b| b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect()
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Session {
+    pub groups: Vec<GroupSession>,
+    pub windows: Vec<WindowSession>,
+}
+
+impl Session {
+    fn path(name: &str) -> Result<PathBuf> {
+        if name.is_empty() || name.contains('/') {
+            return Err(format_err!("Invalid session name: {:?}", name));
+        }
+        let xdg_dirs = xdg::BaseDirectories::with_prefix("lanta")?;
+        let path = xdg_dirs
+            .place_data_file(PathBuf::from("sessions").join(name))
+            .context("Could not create sessions directory")?;
+        Ok(path)
+    }
+
+    pub fn save(&self, name: &str) -> Result<()> {
+        let path = Session::path(name)?;
+        fs::write(&path, self.serialize())
+            .with_context(|_| format!("Could not write session: {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn load(name: &str) -> Result<Session> {
+        let path = Session::path(name)?;
+        let contents = fs::read_to_string(&path)
+            .with_context(|_| format!("Could not read session: {}", path.display()))?;
+        Session::parse(&contents)
+    }
+
+    fn serialize(&self) -> String {
+        // Fields are separated by tabs and records by newlines, so neither
+        // may appear within a field.
+        fn field(s: &str) -> String {
+            s.replace(['\t', '\n'], " ")
+        }
+
+        let mut out = String::new();
+        for group in &self.groups {
+            out.push_str(&format!(
+                "group\t{}\t{}\n",
+                field(&group.name),
+                field(&group.layout)
+            ));
+        }
+        for window in &self.windows {
+            out.push_str(&format!(
+                "window\t{}\t{}\t{}\t{}",
+                field(&window.group),
+                field(&window.instance),
+                field(&window.class),
+                field(&window.title)
+            ));
+            for arg in &window.command {
+                out.push('\t');
+                out.push_str(&field(arg));
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    fn parse(contents: &str) -> Result<Session> {
+        let mut session = Session::default();
+        for (i, line) in contents.lines().enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields.as_slice() {
+                ["group", name, layout] => session.groups.push(GroupSession {
+                    name: name.to_string(),
+                    layout: layout.to_string(),
+                }),
+                ["window", group, instance, class, title, command @ ..] => {
+                    session.windows.push(WindowSession {
+                        group: group.to_string(),
+                        instance: instance.to_string(),
+                        class: class.to_string(),
+                        title: title.to_string(),
+                        command: command.iter().map(|arg| arg.to_string()).collect(),
+                    })
+                }
+                _ => return Err(format_err!("Invalid session on line {}: {}", i + 1, line)),
+            }
+        }
+        Ok(session)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn session() -> Session {
+        Session {
+            groups: vec![GroupSession {
+                name: "chrome".to_owned(),
+                layout: "stack".to_owned(),
+            }],
+            windows: vec![WindowSession {
+                group: "chrome".to_owned(),
+                instance: "google-chrome".to_owned(),
+                class: "Google-chrome".to_owned(),
+                title: "New Tab".to_owned(),
+                command: vec![
+                    "/opt/google/chrome/chrome".to_owned(),
+                    "--profile-directory=Work".to_owned(),
+                ],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let session = session();
+        assert_eq!(Session::parse(&session.serialize()).unwrap(), session);
+    }
+
+    #[test]
+    fn test_serialize_strips_separators() {
+        let mut session = session();
+        session.windows[0].title = "a\tb\nc".to_owned();
+        let parsed = Session::parse(&session.serialize()).unwrap();
+        assert_eq!(parsed.windows[0].title, "a b c");
+    }
+
+    #[test]
+    fn test_parse_without_command() {
+        let parsed = Session::parse("window\tchrome\tchromium\tChromium\tNew Tab\n").unwrap();
+        assert!(parsed.windows[0].command.is_empty());
+        assert_eq!(parsed.windows[0].launch_command(), ("chromium", &[][..]));
+    }
+
+    #[test]
+    fn test_parse_command_line() {
+        assert_eq!(
+            parse_command_line(b"urxvt\0-e\0vim\0"),
+            vec!["urxvt".to_owned(), "-e".to_owned(), "vim".to_owned()]
+        );
+        assert!(parse_command_line(b"").is_empty());
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(Session::parse("window\tchrome\n").is_err());
+    }
+}
@@ -48,6 +48,22 @@ impl<T> Stack<T> {
         self.after.push_front(value);
     }
 
+    /// Adds an element to the stack (at the end) without changing focus.
+    ///
+    /// If the stack is empty, the element will be focused.
+    pub fn push_unfocused(&mut self, value: T) {
+        self.after.push_back(value);
+    }
+
+    /// Adds an element to the stack directly after the focused element, and
+    /// focuses it.
+    pub fn insert_after_focused(&mut self, value: T) {
+        if let Some(focused) = self.after.pop_front() {
+            self.before.push_back(focused);
+        }
+        self.after.push_front(value);
+    }
+
     /// Returns an iterator over the elements in order, ignoring focus.
     pub fn iter(&self) -> impl Iterator<Item = &T> {
         self.before.iter().chain(self.after.iter())
@@ -60,12 +76,22 @@ impl<T> Stack<T> {
 
     /// Returns a reference to the focused element.
     pub fn focused(&self) -> Option<&T> {
-        self.after.get(0)
+        self.after.front()
+    }
+
+    /// Returns the position of the focused element in the stack, starting at
+    /// 0, or `None` if the stack is empty.
+    pub fn focused_index(&self) -> Option<usize> {
+        if self.after.is_empty() {
+            None
+        } else {
+            Some(self.before.len())
+        }
     }
 
     /// Returns a mutable reference to the focued element.
     pub fn focused_mut(&mut self) -> Option<&mut T> {
-        self.after.get_mut(0)
+        self.after.front_mut()
     }
 
     // If there is no element focused, try to focus the last element.
@@ -134,6 +160,39 @@ impl<T> Stack<T> {
         }
     }
 
+    /// Returns a copy of the stack containing only the elements that match the
+    /// predicate.
+    ///
+    /// If the focused element doesn't match the predicate, focus shifts as
+    /// though it had been removed.
+    pub fn filtered<P>(&self, mut p: P) -> Stack<T>
+    where
+        T: Clone,
+        P: FnMut(&T) -> bool,
+    {
+        let mut stack = Stack {
+            before: self.before.iter().filter(|e| p(e)).cloned().collect(),
+            after: self.after.iter().filter(|e| p(e)).cloned().collect(),
+        };
+        stack.ensure_after_not_empty();
+        stack
+    }
+
+    /// Focuses the element at `index`, without changing the order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of range.
+    pub fn focus_index(&mut self, index: usize) {
+        assert!(index < self.len(), "focus_index out of range: {}", index);
+        while self.before.len() > index {
+            self.after.push_front(self.before.pop_back().unwrap());
+        }
+        while self.before.len() < index {
+            self.before.push_back(self.after.pop_front().unwrap());
+        }
+    }
+
     /// Shifts focus to the next element.
     pub fn focus_next(&mut self) {
         if self.len() < 2 {
@@ -176,6 +235,77 @@ impl<T> Stack<T> {
             self.before.extend(self.after.drain(1..));
         }
     }
+
+    /// Swaps the currently focused element with the next element.
+    ///
+    /// Unlike [`shuffle_next()`], this does nothing if the focused element is
+    /// already at the end of the stack.
+    ///
+    /// [`shuffle_next()`]: #method.shuffle_next
+    pub fn move_next(&mut self) {
+        if self.after.len() > 1 {
+            self.before.push_back(self.after.remove(1).unwrap());
+        }
+    }
+
+    /// Swaps the currently focused element with the previous element.
+    ///
+    /// Unlike [`shuffle_previous()`], this does nothing if the focused element
+    /// is already at the front of the stack.
+    ///
+    /// [`shuffle_previous()`]: #method.shuffle_previous
+    pub fn move_previous(&mut self) {
+        if !self.after.is_empty() && !self.before.is_empty() {
+            self.after.insert(1, self.before.pop_back().unwrap());
+        }
+    }
+
+    /// Reverses the order of the stack, keeping the same element focused.
+    pub fn reverse(&mut self) {
+        if let Some(focused) = self.after.pop_front() {
+            let before = self.after.drain(..).rev().collect();
+            self.after = self.before.drain(..).rev().collect();
+            self.after.push_front(focused);
+            self.before = before;
+        }
+    }
+
+    /// Swaps the currently focused element with the first element of the
+    /// stack, keeping it focused.
+    ///
+    /// Does nothing if the focused element is already first.
+    pub fn swap_focused_with_front(&mut self) {
+        if self.before.is_empty() {
+            return;
+        }
+        let focused = self.after.pop_front().unwrap();
+        let front = self.before.pop_front().unwrap();
+        self.after.push_front(front);
+        while let Some(elem) = self.before.pop_back() {
+            self.after.push_front(elem);
+        }
+        self.after.push_front(focused);
+    }
+
+    /// Moves the currently focused element to the front of the stack, keeping
+    /// it focused.
+    pub fn move_focused_to_front(&mut self) {
+        if let Some(focused) = self.after.pop_front() {
+            while let Some(elem) = self.before.pop_back() {
+                self.after.push_front(elem);
+            }
+            self.after.push_front(focused);
+        }
+    }
+
+    /// Moves the currently focused element to the end of the stack, keeping it
+    /// focused.
+    pub fn move_focused_to_back(&mut self) {
+        if let Some(focused) = self.after.pop_front() {
+            self.before.extend(self.after.drain(..));
+            self.after.push_front(focused);
+        }
+    }
 }
 
 impl<T> Default for Stack<T> {
@@ -237,6 +367,7 @@ mod test {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_is_empty() {
         let stack = Stack::<u8>::default();
         assert_eq!(stack.is_empty(), true);
@@ -258,6 +389,45 @@ mod test {
         assert_eq!(stack, vec![2, 3]);
     }
 
+    #[test]
+    fn test_push_unfocused() {
+        let mut stack = Stack::<u8>::new();
+        stack.push_unfocused(2);
+        assert_eq!(stack, vec![2]);
+        assert_eq!(stack.focused(), Some(&2));
+        stack.push_unfocused(3);
+        assert_eq!(stack.focused(), Some(&2));
+        assert_eq!(stack, vec![2, 3]);
+
+        let mut stack = stack_from_pieces(vec![1, 2], vec![3, 4]);
+        stack.push_unfocused(5);
+        assert_eq!(stack.focused(), Some(&3));
+        assert_eq!(stack, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_focused_index() {
+        let stack: Stack<u8> = stack_from_pieces(vec![], vec![]);
+        assert_eq!(stack.focused_index(), None);
+        let stack = stack_from_pieces(vec![], vec![2, 3, 4]);
+        assert_eq!(stack.focused_index(), Some(0));
+        let stack = stack_from_pieces(vec![2], vec![3, 4]);
+        assert_eq!(stack.focused_index(), Some(1));
+    }
+
+    #[test]
+    fn test_insert_after_focused() {
+        let mut stack = Stack::<u8>::new();
+        stack.insert_after_focused(2);
+        assert_eq!(stack, vec![2]);
+        assert_eq!(stack.focused(), Some(&2));
+
+        let mut stack = stack_from_pieces(vec![1, 2], vec![3, 4]);
+        stack.insert_after_focused(5);
+        assert_eq!(stack.focused(), Some(&5));
+        assert_eq!(stack, vec![1, 2, 3, 5, 4]);
+    }
+
     #[test]
     fn test_focused() {
         let stack = stack_from_pieces(vec![], vec![2]);
@@ -298,6 +468,24 @@ mod test {
         assert_eq!(stack.focused(), None);
     }
 
+    #[test]
+    fn test_filtered() {
+        let stack = stack_from_pieces(vec![1, 2], vec![3, 4]);
+        let filtered = stack.filtered(|v| v != &2);
+        assert_eq!(filtered, vec![1, 3, 4]);
+        assert_eq!(filtered.focused(), Some(&3));
+
+        // Focus shifts to the next element if the focused one is filtered out.
+        let filtered = stack.filtered(|v| v != &3);
+        assert_eq!(filtered, vec![1, 2, 4]);
+        assert_eq!(filtered.focused(), Some(&4));
+
+        // ... or to the last element, if there is no next element.
+        let filtered = stack.filtered(|v| v < &3);
+        assert_eq!(filtered, vec![1, 2]);
+        assert_eq!(filtered.focused(), Some(&2));
+    }
+
     #[test]
     fn test_iter() {
         let mut stack = Stack::<u8>::new();
@@ -409,4 +597,132 @@ mod test {
         stack.shuffle_previous();
         assert_eq!(stack, vec![2, 3, 4]);
     }
+
+    #[test]
+    fn test_move_next() {
+        let mut stack = Stack::from(vec![2, 3, 4]);
+        assert_eq!(stack.focused(), Some(&2));
+
+        stack.move_next();
+        assert_eq!(stack, vec![3, 2, 4]);
+        assert_eq!(stack.focused(), Some(&2));
+        stack.move_next();
+        assert_eq!(stack, vec![3, 4, 2]);
+        assert_eq!(stack.focused(), Some(&2));
+        // Doesn't wrap around once at the end:
+        stack.move_next();
+        assert_eq!(stack, vec![3, 4, 2]);
+        assert_eq!(stack.focused(), Some(&2));
+    }
+
+    #[test]
+    fn test_move_previous() {
+        let mut stack = Stack::<u8>::new();
+        stack.push(2);
+        stack.push(3);
+        stack.push(4);
+        assert_eq!(stack.focused(), Some(&4));
+
+        stack.move_previous();
+        assert_eq!(stack, vec![2, 4, 3]);
+        assert_eq!(stack.focused(), Some(&4));
+        stack.move_previous();
+        assert_eq!(stack, vec![4, 2, 3]);
+        assert_eq!(stack.focused(), Some(&4));
+        // Doesn't wrap around once at the front:
+        stack.move_previous();
+        assert_eq!(stack, vec![4, 2, 3]);
+        assert_eq!(stack.focused(), Some(&4));
+    }
+
+    #[test]
+    fn test_move_focused_to_front() {
+        let mut stack = stack_from_pieces(vec![2, 3], vec![4, 5]);
+        assert_eq!(stack.focused(), Some(&4));
+
+        stack.move_focused_to_front();
+        assert_eq!(stack, vec![4, 2, 3, 5]);
+        assert_eq!(stack.focused(), Some(&4));
+        // Already at the front:
+        stack.move_focused_to_front();
+        assert_eq!(stack, vec![4, 2, 3, 5]);
+        assert_eq!(stack.focused(), Some(&4));
+    }
+
+    #[test]
+    fn test_move_focused_to_back() {
+        let mut stack = stack_from_pieces(vec![2], vec![3, 4, 5]);
+        assert_eq!(stack.focused(), Some(&3));
+
+        stack.move_focused_to_back();
+        assert_eq!(stack, vec![2, 4, 5, 3]);
+        assert_eq!(stack.focused(), Some(&3));
+        // Already at the back:
+        stack.move_focused_to_back();
+        assert_eq!(stack, vec![2, 4, 5, 3]);
+        assert_eq!(stack.focused(), Some(&3));
+    }
+
+    #[test]
+    fn test_swap_focused_with_front() {
+        let mut stack = Stack::<u8>::new();
+        stack.push(2);
+        stack.push(3);
+        stack.push(4);
+        assert_eq!(stack.focused(), Some(&4));
+
+        assert_eq!(stack, vec![2, 3, 4]);
+        stack.swap_focused_with_front();
+        assert_eq!(stack, vec![4, 3, 2]);
+        assert_eq!(stack.focused(), Some(&4));
+        // Already at the front:
+        stack.swap_focused_with_front();
+        assert_eq!(stack, vec![4, 3, 2]);
+        assert_eq!(stack.focused(), Some(&4));
+    }
+
+    #[test]
+    fn test_focus_index() {
+        let mut stack = Stack::from(vec![2, 3, 4]);
+
+        stack.focus_index(0);
+        assert_eq!(stack.focused(), Some(&2));
+        stack.focus_index(1);
+        assert_eq!(stack.focused(), Some(&3));
+        stack.focus_index(2);
+        assert_eq!(stack.focused(), Some(&4));
+        stack.focus_index(0);
+        assert_eq!(stack.focused(), Some(&2));
+        // Order is unchanged:
+        assert_eq!(stack, vec![2, 3, 4]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_focus_index_out_of_range() {
+        let mut stack = Stack::from(vec![2, 3, 4]);
+        stack.focus_index(3);
+    }
+
+    #[test]
+    fn test_reverse() {
+        let mut stack = stack_from_pieces(vec![1], vec![2, 3]);
+        assert_eq!(stack.focused(), Some(&2));
+
+        stack.reverse();
+        assert_eq!(stack, vec![3, 2, 1]);
+        assert_eq!(stack.focused(), Some(&2));
+        stack.reverse();
+        assert_eq!(stack, vec![1, 2, 3]);
+        assert_eq!(stack.focused(), Some(&2));
+
+        let mut stack = stack_from_pieces(vec![], vec![1, 2, 3]);
+        stack.reverse();
+        assert_eq!(stack, vec![3, 2, 1]);
+        assert_eq!(stack.focused(), Some(&1));
+
+        let mut stack: Stack<u8> = Stack::new();
+        stack.reverse();
+        assert_eq!(stack, vec![]);
+    }
 }
@@ -0,0 +1,156 @@
+//! Draws text using the X server's core fonts, for anything lanta draws
+//! itself (e.g. a bar or a prompt).
+//!
+//! Core fonts only cover Latin-1 and have no anti-aliasing, but they need
+//! nothing beyond the X server, so this works wherever lanta does.
+
+use failure::format_err;
+
+use crate::Result;
+
+/// The core font every X server provides, used if the requested font can't
+/// be opened.
+const FALLBACK_FONT: &str = "fixed";
+
+/// The most characters that fit in a single `PolyText8` text item.
+const MAX_ITEM_LENGTH: usize = 254;
+
+/// A core X font, opened on the server.
+///
+/// The font must be closed with `close()` once it's no longer needed.
+pub struct Font {
+    id: xcb::Font,
+    ascent: i16,
+    descent: i16,
+    /// The first character in `widths`.
+    min_char: u16,
+    /// The width of each character from `min_char` onwards.
+    widths: Vec<i16>,
+    /// The width of characters missing from `widths`.
+    default_width: i16,
+}
+
+impl Font {
+    /// Opens the font with the given X logical font description (e.g.
+    /// `-misc-fixed-medium-r-normal--13-*`), falling back to `fixed` if it
+    /// doesn't exist.
+    pub fn open(conn: &xcb::Connection, name: &str) -> Result<Font> {
+        let id = conn.generate_id();
+        let opened = xcb::open_font_checked(conn, id, name).request_check();
+        if opened.is_err() {
+            warn!("Could not open font {}, using {}", name, FALLBACK_FONT);
+            xcb::open_font_checked(conn, id, FALLBACK_FONT)
+                .request_check()
+                .map_err(|_| format_err!("Could not open font: {}", FALLBACK_FONT))?;
+        }
+
+        let reply = xcb::query_font(conn, id)
+            .get_reply()
+            .map_err(|_| format_err!("Could not query font: {}", name))?;
+        Ok(Font {
+            id,
+            ascent: reply.font_ascent(),
+            descent: reply.font_descent(),
+            min_char: reply.min_char_or_byte2(),
+            widths: reply
+                .char_infos()
+                .map(|info| info.character_width())
+                .collect(),
+            default_width: reply.max_bounds().character_width(),
+        })
+    }
+
+    /// Returns the distance from the baseline to the top of the tallest
+    /// character.
+    pub fn ascent(&self) -> i16 {
+        self.ascent
+    }
+
+    /// Returns the height of a line of text.
+    pub fn height(&self) -> u16 {
+        (self.ascent + self.descent) as u16
+    }
+
+    /// Returns the width of `text` when drawn in this font, in pixels.
+    pub fn text_width(&self, text: &str) -> u32 {
+        latin1(text)
+            .iter()
+            .map(|&c| {
+                let index = usize::from(c).wrapping_sub(usize::from(self.min_char));
+                let width = self
+                    .widths
+                    .get(index)
+                    .cloned()
+                    .unwrap_or(self.default_width);
+                width.max(0) as u32
+            })
+            .sum()
+    }
+
+    /// Draws `text` onto `drawable` in `color`, with the baseline of the text
+    /// starting at (`x`, `y`).
+    ///
+    /// Characters outside of Latin-1 are drawn as `?`.
+    pub fn draw(
+        &self,
+        conn: &xcb::Connection,
+        drawable: xcb::Drawable,
+        x: i16,
+        y: i16,
+        color: u32,
+        text: &str,
+    ) {
+        let gc = conn.generate_id();
+        xcb::create_gc(
+            conn,
+            gc,
+            drawable,
+            &[(xcb::GC_FOREGROUND, color), (xcb::GC_FONT, self.id)],
+        );
+        xcb::poly_text_8(conn, drawable, gc, x, y, &text_items(&latin1(text)));
+        xcb::free_gc(conn, gc);
+    }
+
+    /// Closes the font on the X server.
+    pub fn close(self, conn: &xcb::Connection) {
+        xcb::close_font(conn, self.id);
+    }
+}
+
+/// Encodes `text` as Latin-1, replacing any characters outside of it.
+fn latin1(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| if (c as u32) < 256 { c as u8 } else { b'?' })
+        .collect()
+}
+
+/// Splits `text` into the `TEXTITEM8`s expected by `PolyText8`: each is the
+/// length of the string, a horizontal offset (always 0) and then the string.
+fn text_items(text: &[u8]) -> Vec<u8> {
+    let mut items = Vec::with_capacity(text.len() + 2 * (text.len() / MAX_ITEM_LENGTH + 1));
+    for chunk in text.chunks(MAX_ITEM_LENGTH) {
+        items.push(chunk.len() as u8);
+        items.push(0);
+        items.extend_from_slice(chunk);
+    }
+    items
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_latin1_replaces_other_characters() {
+        assert_eq!(latin1("café ☕"), b"caf\xe9 ?".to_vec());
+    }
+
+    #[test]
+    fn test_text_items_splits_long_text() {
+        let text = vec![b'a'; MAX_ITEM_LENGTH + 1];
+        let items = text_items(&text);
+        assert_eq!(items.len(), text.len() + 4);
+        assert_eq!(&items[..2], &[MAX_ITEM_LENGTH as u8, 0]);
+        assert_eq!(&items[MAX_ITEM_LENGTH + 2..], &[1, 0, b'a']);
+    }
+}
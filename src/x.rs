@@ -1,27 +1,105 @@
-use std::collections::HashMap;
+use std::cell::Cell;
+use std::cmp;
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
+use std::os::unix::io::AsRawFd;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::time::{Duration, Instant};
 
 use failure::{format_err, ResultExt};
 use xcb_util::keysyms::KeySymbols;
 use xcb_util::{ewmh, icccm};
 
 use crate::groups::Group;
-use crate::keys::{KeyCombo, KeyHandlers};
+use crate::keys::{ButtonCombo, KeyCombo, KeyHandlers, ModKey};
 use crate::stack::Stack;
 use crate::Result;
 
 pub use self::ewmh::StrutPartial;
 
+/// The legacy _NET_WM_STRUT property, which only has the width reserved on
+/// each edge of the screen.
+pub type Strut = ewmh::Extents;
+
+/// Set once we've been asked to quit, after which the `EventLoop` yields no
+/// more events.
+static QUIT_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// The read and write ends of a pipe which `request_quit()` writes to, so that
+/// the `EventLoop` wakes up even if a signal arrives just before it waits.
+/// Both are -1 until `quit_on_signals()` creates the pipe.
+static QUIT_PIPE_READ: AtomicI32 = AtomicI32::new(-1);
+static QUIT_PIPE_WRITE: AtomicI32 = AtomicI32::new(-1);
+
+/// Makes the `EventLoop` stop yielding events, once the event currently being
+/// handled (if any) is done with.
+///
+/// This is safe to call from a signal handler.
+pub fn request_quit() {
+    QUIT_REQUESTED.store(true, Ordering::SeqCst);
+    let fd = QUIT_PIPE_WRITE.load(Ordering::SeqCst);
+    if fd >= 0 {
+        // If the pipe is full, the event loop is already due to wake up.
+        unsafe {
+            libc::write(fd, [0u8].as_ptr() as *const libc::c_void, 1);
+        }
+    }
+}
+
+/// Calls `request_quit()` when we receive SIGTERM or SIGINT.
+pub fn quit_on_signals() {
+    extern "C" fn on_signal(_signal: libc::c_int) {
+        request_quit();
+    }
+
+    let mut fds = [-1; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC) } == 0 {
+        QUIT_PIPE_READ.store(fds[0], Ordering::SeqCst);
+        QUIT_PIPE_WRITE.store(fds[1], Ordering::SeqCst);
+    } else {
+        warn!("Could not create quit pipe, signals may not wake the event loop");
+    }
+
+    let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGTERM, handler);
+        libc::signal(libc::SIGINT, handler);
+    }
+}
+
+/// Identifies a property (or other named thing) on the X server.
+pub type Atom = xcb::Atom;
+
+/// ICCCM's WM_HINTS property.
+pub const WM_HINTS: Atom = xcb::ATOM_WM_HINTS;
+
+/// An X server timestamp, in milliseconds.
+pub type Timestamp = xcb::Timestamp;
+
+/// Returns whether timestamp `a` is later than timestamp `b`, allowing for
+/// the X server's clock wrapping around.
+pub fn is_later(a: Timestamp, b: Timestamp) -> bool {
+    a != b && a.wrapping_sub(b) < (1 << 31)
+}
+
 /// A handle to an X Window.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct WindowId(xcb::Window);
 
 impl WindowId {
-    fn to_x(&self) -> xcb::Window {
+    fn to_x(self) -> xcb::Window {
         self.0
     }
 }
 
+/// Lets tests make up window IDs without a connection.
+#[cfg(test)]
+impl From<u32> for WindowId {
+    fn from(id: u32) -> WindowId {
+        WindowId(id)
+    }
+}
+
 impl fmt::Display for WindowId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.0)
@@ -62,9 +140,39 @@ pub enum WindowState {
     DemandsAttention,
 }
 
+/// How a client asks for one of its window's states to be changed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum StateAction {
+    Remove,
+    Add,
+    Toggle,
+}
+
+impl StateAction {
+    fn from_x(action: u32) -> Option<StateAction> {
+        match action {
+            0 => Some(StateAction::Remove),
+            1 => Some(StateAction::Add),
+            2 => Some(StateAction::Toggle),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the state should be set, given whether it is currently
+    /// set.
+    pub fn apply(self, current: bool) -> bool {
+        match self {
+            StateAction::Remove => false,
+            StateAction::Add => true,
+            StateAction::Toggle => !current,
+        }
+    }
+}
+
 macro_rules! atoms {
     ( $( $name:ident ),+ ) => {
         #[allow(non_snake_case)]
+        #[derive(Debug)]
         struct InternedAtoms {
             $(
                 pub $name: xcb::Atom
@@ -85,7 +193,222 @@ macro_rules! atoms {
     ( $( $name:ident ),+ , ) => (atoms!($( $name ),+);)
 }
 
-atoms!(WM_DELETE_WINDOW, WM_PROTOCOLS,);
+atoms!(WM_DELETE_WINDOW, WM_PROTOCOLS, WM_STATE,);
+
+/// Where X should move input focus if the focused window is destroyed or
+/// unmapped.
+///
+/// Lanta always focuses the next window in the group itself when a window goes
+/// away, so this only decides where focus is in the brief moment before that.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FocusRevert {
+    /// Revert to the window's parent. For top-level windows, this is the root
+    /// window. This is the default.
+    Parent,
+    /// Revert to whichever root window the pointer is on, making focus briefly
+    /// follow the mouse.
+    PointerRoot,
+    /// Revert to no window, dropping keyboard input.
+    Nothing,
+}
+
+impl FocusRevert {
+    fn to_x(self) -> u8 {
+        match self {
+            FocusRevert::Parent => xcb::INPUT_FOCUS_PARENT as u8,
+            FocusRevert::PointerRoot => xcb::INPUT_FOCUS_POINTER_ROOT as u8,
+            FocusRevert::Nothing => xcb::INPUT_FOCUS_NONE as u8,
+        }
+    }
+}
+
+/// The instance and class names from ICCCM's WM_CLASS property.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WmClass {
+    pub instance: String,
+    pub class: String,
+}
+
+/// The size constraints from ICCCM's WM_NORMAL_HINTS property.
+///
+/// Sizes are `(width, height)`. Aspect ratios are `(min, max)`, each a
+/// `(numerator, denominator)` ratio of width to height.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SizeHints {
+    pub min_size: Option<(u32, u32)>,
+    pub max_size: Option<(u32, u32)>,
+    pub base_size: Option<(u32, u32)>,
+    pub resize_inc: Option<(u32, u32)>,
+    pub aspect: Option<((u32, u32), (u32, u32))>,
+    /// Whether the user or the program asked for the window's position.
+    pub position: bool,
+}
+
+impl SizeHints {
+    const US_POSITION: u32 = 1;
+    const P_POSITION: u32 = 1 << 2;
+    const MIN_SIZE: u32 = 1 << 4;
+    const MAX_SIZE: u32 = 1 << 5;
+    const RESIZE_INC: u32 = 1 << 6;
+    const ASPECT: u32 = 1 << 7;
+    const BASE_SIZE: u32 = 1 << 8;
+
+    /// Parses the 18 CARD32s of a WM_SIZE_HINTS property.
+    ///
+    /// We don't use `icccm::get_wm_normal_hints()`, as the `SizeHints` it
+    /// returns checks its flags incorrectly.
+    fn from_values(values: &[u32]) -> Option<SizeHints> {
+        if values.len() < 17 {
+            return None;
+        }
+        let flags = values[0];
+        let pair = |flag, i: usize| {
+            if flags & flag != 0 {
+                Some((values[i], values[i + 1]))
+            } else {
+                None
+            }
+        };
+        Some(SizeHints {
+            min_size: pair(SizeHints::MIN_SIZE, 5),
+            max_size: pair(SizeHints::MAX_SIZE, 7),
+            resize_inc: pair(SizeHints::RESIZE_INC, 9),
+            aspect: pair(SizeHints::ASPECT, 11)
+                .and_then(|min| Some((min, pair(SizeHints::ASPECT, 13)?))),
+            base_size: pair(SizeHints::BASE_SIZE, 15),
+            position: flags & (SizeHints::US_POSITION | SizeHints::P_POSITION) != 0,
+        })
+    }
+
+    /// Returns the largest size no bigger than `(width, height)` which
+    /// satisfies the hints' aspect ratio, resize increments and maximum size,
+    /// and which is at least the minimum size.
+    pub fn constrain(&self, width: u32, height: u32) -> (u32, u32) {
+        // As in ICCCM, the base size defaults to the minimum size and vice
+        // versa.
+        let base = self.base_size.or(self.min_size).unwrap_or((0, 0));
+        let min = self.min_size.or(self.base_size).unwrap_or((0, 0));
+        let (mut width, mut height) = (width, height);
+
+        if let Some(((min_x, min_y), (max_x, max_y))) = self.aspect {
+            let w = width.saturating_sub(base.0) as f64;
+            let h = height.saturating_sub(base.1) as f64;
+            if min_y > 0 && max_y > 0 && w > 0.0 && h > 0.0 {
+                let min_aspect = f64::from(min_x) / f64::from(min_y);
+                let max_aspect = f64::from(max_x) / f64::from(max_y);
+                if w / h > max_aspect {
+                    width = base.0 + (h * max_aspect).round() as u32;
+                } else if min_aspect > 0.0 && w / h < min_aspect {
+                    height = base.1 + (w / min_aspect).round() as u32;
+                }
+            }
+        }
+
+        if let Some((inc_width, inc_height)) = self.resize_inc {
+            if inc_width > 0 {
+                width -= width.saturating_sub(base.0) % inc_width;
+            }
+            if inc_height > 0 {
+                height -= height.saturating_sub(base.1) % inc_height;
+            }
+        }
+
+        if let Some((max_width, max_height)) = self.max_size {
+            if max_width > 0 {
+                width = cmp::min(width, max_width);
+            }
+            if max_height > 0 {
+                height = cmp::min(height, max_height);
+            }
+        }
+
+        (cmp::max(width, min.0), cmp::max(height, min.1))
+    }
+}
+
+/// The states of ICCCM's WM_STATE property which we set on managed windows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WmState {
+    Normal,
+    /// The window is minimized (hidden).
+    Iconic,
+}
+
+impl WmState {
+    fn to_x(self) -> u32 {
+        match self {
+            WmState::Normal => 1,
+            WmState::Iconic => 3,
+        }
+    }
+
+    fn from_x(state: u32) -> Option<WmState> {
+        match state {
+            1 => Some(WmState::Normal),
+            3 => Some(WmState::Iconic),
+            _ => None,
+        }
+    }
+}
+
+/// The parts of ICCCM's WM_HINTS property that we're interested in.
+///
+/// We read the property ourselves rather than using `icccm::get_wm_hints()`, as
+/// `icccm::WmHints` doesn't correctly check the flags of most of its fields.
+struct WmHints {
+    flags: u32,
+    initial_state: u32,
+    window_group: xcb::Window,
+}
+
+impl WmHints {
+    const STATE_HINT: u32 = 1 << 1;
+    const WINDOW_GROUP_HINT: u32 = 1 << 6;
+    const URGENCY_HINT: u32 = 1 << 8;
+
+    /// The `initial_state` of a window which wants to start minimized.
+    const ICONIC_STATE: u32 = 3;
+
+    fn from_values(values: &[u32]) -> Option<WmHints> {
+        if values.len() < 9 {
+            return None;
+        }
+        Some(WmHints {
+            flags: values[0],
+            initial_state: values[2],
+            window_group: values[8],
+        })
+    }
+}
+
+/// A window showing a scaled-down copy of another window's contents, kept up
+/// to date as the other window is drawn to.
+///
+/// Use `Connection::create_thumbnail()` to create one, and
+/// `Connection::destroy_thumbnail()` once it's no longer needed.
+#[cfg(feature = "pip")]
+pub struct Thumbnail {
+    source: WindowId,
+    window: WindowId,
+    damage: xcb::damage::Damage,
+    source_picture: xcb::render::Picture,
+    picture: xcb::render::Picture,
+    width: u16,
+    height: u16,
+}
+
+#[cfg(feature = "pip")]
+impl Thumbnail {
+    /// Returns the window whose contents are being shown.
+    pub fn source(&self) -> WindowId {
+        self.source
+    }
+
+    /// Returns the window the thumbnail is drawn in.
+    pub fn window(&self) -> WindowId {
+        self.window
+    }
+}
 
 pub struct Connection {
     conn: ewmh::Connection,
@@ -94,6 +417,15 @@ pub struct Connection {
     atoms: InternedAtoms,
     window_type_lookup: HashMap<xcb::Atom, WindowType>,
     window_state_lookup: HashMap<xcb::Atom, WindowState>,
+    focus_revert: Cell<FocusRevert>,
+    /// The width of the borders we draw around windows, which
+    /// `configure_window()` leaves room for.
+    border_width: Cell<u32>,
+    /// The window named by _NET_SUPPORTING_WM_CHECK, once we've installed
+    /// ourselves as the window manager.
+    wm_check_window: Cell<Option<WindowId>>,
+    /// How often the `EventLoop` yields `Event::Timer`, if at all.
+    timer: Cell<Option<Duration>>,
 }
 
 impl Connection {
@@ -154,6 +486,10 @@ impl Connection {
             atoms,
             window_type_lookup: types,
             window_state_lookup: state,
+            focus_revert: Cell::new(FocusRevert::Parent),
+            border_width: Cell::new(0),
+            wm_check_window: Cell::new(None),
+            timer: Cell::new(None),
         })
     }
 
@@ -181,18 +517,192 @@ impl Connection {
 
         self.enable_window_key_events(&self.root, key_handlers);
 
+        // EWMH's handshake for announcing a compliant window manager: an
+        // unmapped child window named after the WM, which both it and the
+        // root window point to with _NET_SUPPORTING_WM_CHECK.
+        let check_window = self.conn.generate_id();
+        xcb::create_window(
+            &self.conn,
+            xcb::COPY_FROM_PARENT as u8,
+            check_window,
+            self.root.to_x(),
+            -1,
+            -1,
+            1,
+            1,
+            0,
+            xcb::WINDOW_CLASS_INPUT_ONLY as u16,
+            xcb::COPY_FROM_PARENT,
+            &[],
+        );
+        ewmh::set_supporting_wm_check(&self.conn, self.root.to_x(), check_window);
+        ewmh::set_supporting_wm_check(&self.conn, check_window, check_window);
+        ewmh::set_wm_name(&self.conn, check_window, "lanta");
+        self.wm_check_window.set(Some(WindowId(check_window)));
+
+        ewmh::set_supported(&self.conn, self.screen_idx, &self.supported_atoms());
+
         Ok(())
     }
 
+    /// Returns the EWMH atoms we honor, which we advertise in _NET_SUPPORTED.
+    ///
+    /// Add to this as support for more of EWMH lands.
+    fn supported_atoms(&self) -> Vec<xcb::Atom> {
+        let conn = &self.conn;
+        vec![
+            conn.SUPPORTED(),
+            conn.SUPPORTING_WM_CHECK(),
+            conn.WM_NAME(),
+            conn.CLIENT_LIST(),
+            conn.NUMBER_OF_DESKTOPS(),
+            conn.DESKTOP_NAMES(),
+            conn.DESKTOP_VIEWPORT(),
+            conn.CURRENT_DESKTOP(),
+            conn.ACTIVE_WINDOW(),
+            conn.SHOWING_DESKTOP(),
+            conn.FRAME_EXTENTS(),
+            conn.WM_STRUT(),
+            conn.WM_STRUT_PARTIAL(),
+            conn.WM_WINDOW_TYPE(),
+            conn.WM_WINDOW_TYPE_DOCK(),
+            conn.WM_WINDOW_TYPE_DIALOG(),
+            conn.WM_WINDOW_TYPE_UTILITY(),
+            conn.WM_WINDOW_TYPE_SPLASH(),
+            conn.WM_STATE(),
+            conn.WM_STATE_FULLSCREEN(),
+            conn.WM_STATE_ABOVE(),
+            conn.WM_STATE_BELOW(),
+            conn.WM_STATE_DEMANDS_ATTENTION(),
+            conn.WM_USER_TIME(),
+        ]
+    }
+
+    /// Stops acting as the window manager, undoing `install_as_wm()` and
+    /// removing the EWMH properties we've set on the root window, so that
+    /// another window manager can take over cleanly.
+    pub fn uninstall_as_wm(&self) {
+        let root = self.root.to_x();
+        xcb::ungrab_key(
+            &self.conn,
+            xcb::GRAB_ANY as u8,
+            root,
+            xcb::MOD_MASK_ANY as u16,
+        );
+        xcb::ungrab_button(
+            &self.conn,
+            xcb::BUTTON_INDEX_ANY as u8,
+            root,
+            xcb::MOD_MASK_ANY as u16,
+        );
+        let values = [(xcb::CW_EVENT_MASK, xcb::EVENT_MASK_NO_EVENT)];
+        xcb::change_window_attributes(&self.conn, root, &values);
+
+        let properties = [
+            self.conn.DESKTOP_NAMES(),
+            self.conn.NUMBER_OF_DESKTOPS(),
+            self.conn.CURRENT_DESKTOP(),
+            self.conn.DESKTOP_VIEWPORT(),
+            self.conn.ACTIVE_WINDOW(),
+            self.conn.SHOWING_DESKTOP(),
+            self.conn.CLIENT_LIST(),
+            self.conn.SUPPORTING_WM_CHECK(),
+            self.conn.SUPPORTED(),
+        ];
+        for property in &properties {
+            xcb::delete_property(&self.conn, root, *property);
+        }
+        if let Some(check_window) = self.wm_check_window.take() {
+            xcb::destroy_window(&self.conn, check_window.to_x());
+        }
+
+        xcb::set_input_focus(
+            &self.conn,
+            xcb::INPUT_FOCUS_POINTER_ROOT as u8,
+            xcb::INPUT_FOCUS_POINTER_ROOT,
+            xcb::CURRENT_TIME,
+        );
+        self.flush();
+    }
+
+    /// Hands a window back as we found it: mapped, and without our key and
+    /// button grabs, event mask or border.
+    pub fn release_window(&self, window_id: &WindowId, map: bool) {
+        xcb::ungrab_key(
+            &self.conn,
+            xcb::GRAB_ANY as u8,
+            window_id.to_x(),
+            xcb::MOD_MASK_ANY as u16,
+        );
+        xcb::ungrab_button(
+            &self.conn,
+            xcb::BUTTON_INDEX_ANY as u8,
+            window_id.to_x(),
+            xcb::MOD_MASK_ANY as u16,
+        );
+        self.disable_window_tracking(window_id);
+        self.set_window_border_width(window_id, 0);
+        xcb::delete_property(&self.conn, window_id.to_x(), self.conn.FRAME_EXTENTS());
+        if map {
+            xcb::map_window(&self.conn, window_id.to_x());
+        }
+    }
+
+    /// Sets the window's _NET_WM_DESKTOP: the index of the group it's in.
+    pub fn set_wm_desktop(&self, window_id: &WindowId, index: u32) {
+        ewmh::set_wm_desktop(&self.conn, window_id.to_x(), index);
+    }
+
+    /// Returns the window's _NET_WM_DESKTOP, if it's set.
+    pub fn get_wm_desktop(&self, window_id: &WindowId) -> Option<u32> {
+        ewmh::get_wm_desktop(&self.conn, window_id.to_x())
+            .get_reply()
+            .ok()
+    }
+
+    /// Returns the root window's _NET_CURRENT_DESKTOP, if it's set.
+    pub fn get_current_desktop(&self) -> Option<u32> {
+        ewmh::get_current_desktop(&self.conn, self.screen_idx)
+            .get_reply()
+            .ok()
+    }
+
+    /// Logs details of the X server and our connection to it, for bug reports.
+    pub fn log_diagnostics(&self) {
+        let setup = self.conn.get_setup();
+        info!(
+            "X server: {} (protocol {}.{}, release {})",
+            setup.vendor(),
+            setup.protocol_major_version(),
+            setup.protocol_minor_version(),
+            setup.release_number()
+        );
+        info!("Screen: {}, root window: {}", self.screen_idx, self.root);
+        info!("Interned atoms: {:?}", self.atoms);
+        info!("Window type atoms: {:?}", self.window_type_lookup);
+        info!("Window state atoms: {:?}", self.window_state_lookup);
+    }
+
     /// Returns the ID of the root window.
     pub fn root_window_id(&self) -> &WindowId {
         &self.root
     }
 
+    /// Sets EWMH's _NET_CLIENT_LIST to the windows we're managing, for
+    /// taskbars and tools like `wmctrl`.
+    pub fn update_client_list(&self, windows: &[WindowId]) {
+        let windows: Vec<xcb::Window> = windows.iter().map(|w| w.to_x()).collect();
+        ewmh::set_client_list(&self.conn, self.screen_idx, &windows);
+    }
+
     pub fn update_ewmh_desktops(&self, groups: &Stack<Group>) {
         let group_names = groups.iter().map(|g| g.name());
         ewmh::set_desktop_names(&self.conn, self.screen_idx, group_names);
         ewmh::set_number_of_desktops(&self.conn, self.screen_idx, groups.len() as u32);
+        // Our desktops don't scroll, and we only support a single monitor, so
+        // every desktop's viewport is at the origin.
+        let origins = vec![(0, 0); groups.len()];
+        self.set_desktop_viewport(&origins);
 
         // Matching the current group on name isn't perfect, but it's good enough for
         // EWMH.
@@ -209,6 +719,16 @@ impl Connection {
         };
     }
 
+    /// Sets _NET_DESKTOP_VIEWPORT: the top-left corner of each desktop's
+    /// viewport, as `(x, y)`, in desktop order.
+    pub fn set_desktop_viewport(&self, origins: &[(u32, u32)]) {
+        let coordinates: Vec<ewmh::Coordinates> = origins
+            .iter()
+            .map(|&(x, y)| ewmh::Coordinates { x, y })
+            .collect();
+        ewmh::set_desktop_viewport(&self.conn, self.screen_idx, &coordinates);
+    }
+
     pub fn top_level_windows(&self) -> Result<Vec<WindowId>> {
         let windows = xcb::query_tree(&self.conn, self.root.to_x())
             .get_reply()?
@@ -219,6 +739,34 @@ impl Connection {
         Ok(windows)
     }
 
+    /// Returns whether a top-level window is one we should manage: it's mapped
+    /// and hasn't asked to bypass the window manager (override-redirect).
+    pub fn is_window_manageable(&self, window_id: &WindowId) -> bool {
+        xcb::get_window_attributes(&self.conn, window_id.to_x())
+            .get_reply()
+            .map(|reply| {
+                reply.map_state() == xcb::MAP_STATE_VIEWABLE as u8 && !reply.override_redirect()
+            })
+            .unwrap_or(false)
+    }
+
+    /// Returns the window which has the input focus, if any.
+    pub fn get_input_focus(&self) -> Option<WindowId> {
+        let reply = xcb::get_input_focus(&self.conn).get_reply().ok()?;
+        match reply.focus() {
+            xcb::NONE | xcb::INPUT_FOCUS_POINTER_ROOT => None,
+            window => Some(WindowId(window)),
+        }
+    }
+
+    /// Returns whether the window is mapped (and all of its ancestors are).
+    pub fn is_window_viewable(&self, window_id: &WindowId) -> bool {
+        xcb::get_window_attributes(&self.conn, window_id.to_x())
+            .get_reply()
+            .map(|reply| reply.map_state() == xcb::MAP_STATE_VIEWABLE as u8)
+            .unwrap_or(false)
+    }
+
     /// Queries the WM_PROTOCOLS property of a window, returning a list of the
     /// protocols that it supports.
     fn get_wm_protocols(&self, window_id: &WindowId) -> Result<Vec<xcb::Atom>> {
@@ -258,12 +806,218 @@ impl Connection {
             .unwrap_or_else(|_| Vec::new())
     }
 
+    /// Returns the window's WM_CLASS, or `None` if it isn't set.
+    pub fn get_wm_class(&self, window_id: &WindowId) -> Option<WmClass> {
+        // We don't use `icccm::get_wm_class()`, as it panics on names which
+        // aren't valid UTF-8.
+        let reply = xcb::get_property(
+            &self.conn,
+            false,
+            window_id.to_x(),
+            xcb::ATOM_WM_CLASS,
+            xcb::ATOM_STRING,
+            0,
+            1024,
+        )
+        .get_reply()
+        .ok()?;
+        // An unset property reads back as an empty value.
+        if reply.value_len() == 0 {
+            return None;
+        }
+        let mut names = reply
+            .value::<u8>()
+            .split(|&b| b == 0)
+            .map(|name| String::from_utf8_lossy(name).into_owned());
+        let instance = names.next()?;
+        let class = names.next().unwrap_or_default();
+        Some(WmClass { instance, class })
+    }
+
+    /// Returns the window's title, preferring _NET_WM_NAME over WM_NAME, or
+    /// `None` if neither is set to a title we can decode.
+    pub fn get_window_title(&self, window_id: &WindowId) -> Option<String> {
+        let get_property = |property| {
+            xcb::get_property(
+                &self.conn,
+                false,
+                window_id.to_x(),
+                property,
+                xcb::ATOM_ANY,
+                0,
+                1024,
+            )
+            .get_reply()
+            .ok()
+            .filter(|reply| reply.value_len() > 0)
+        };
+        // _NET_WM_NAME is always UTF-8, but WM_NAME may be Latin-1.
+        get_property(self.conn.WM_NAME())
+            .and_then(|reply| decode_text(reply.value(), false))
+            .or_else(|| {
+                let reply = get_property(xcb::ATOM_WM_NAME)?;
+                decode_text(reply.value(), reply.type_() == xcb::ATOM_STRING)
+            })
+    }
+
+    /// Returns the window's WM_NORMAL_HINTS, or `None` if it isn't set.
+    pub fn get_wm_normal_hints(&self, window_id: &WindowId) -> Option<SizeHints> {
+        let reply = xcb::get_property(
+            &self.conn,
+            false,
+            window_id.to_x(),
+            xcb::ATOM_WM_NORMAL_HINTS,
+            xcb::ATOM_WM_SIZE_HINTS,
+            0,
+            18,
+        )
+        .get_reply()
+        .ok()?;
+        SizeHints::from_values(reply.value::<u32>())
+    }
+
+    fn get_wm_hints(&self, window_id: &WindowId) -> Option<WmHints> {
+        xcb::get_property(
+            &self.conn,
+            false,
+            window_id.to_x(),
+            xcb::ATOM_WM_HINTS,
+            xcb::ATOM_WM_HINTS,
+            0,
+            9,
+        )
+        .get_reply()
+        .ok()
+        .and_then(|reply| WmHints::from_values(reply.value::<u32>()))
+    }
+
+    /// Returns the leader of the window's group, from ICCCM's WM_HINTS.
+    ///
+    /// Applications with several top-level windows may put them all in the
+    /// same group. Returns `None` if the window doesn't specify a group.
+    pub fn get_window_group(&self, window_id: &WindowId) -> Option<WindowId> {
+        self.get_wm_hints(window_id)
+            .filter(|hints| hints.flags & WmHints::WINDOW_GROUP_HINT != 0)
+            .map(|hints| hints.window_group)
+            .filter(|&window| window != xcb::NONE)
+            .map(WindowId)
+    }
+
+    /// Returns whether the window asked to start minimized, by setting the
+    /// initial state in ICCCM's WM_HINTS to IconicState.
+    pub fn is_window_initially_iconic(&self, window_id: &WindowId) -> bool {
+        self.get_wm_hints(window_id).is_some_and(|hints| {
+            hints.flags & WmHints::STATE_HINT != 0 && hints.initial_state == WmHints::ICONIC_STATE
+        })
+    }
+
+    /// Sets ICCCM's WM_STATE on the window, so that clients (e.g. taskbars)
+    /// know whether it's minimized.
+    pub fn set_wm_state(&self, window_id: &WindowId, state: WmState) {
+        let atom = self.atoms.WM_STATE;
+        xcb::change_property(
+            &self.conn,
+            xcb::PROP_MODE_REPLACE as u8,
+            window_id.to_x(),
+            atom,
+            atom,
+            32,
+            &[state.to_x(), xcb::NONE],
+        );
+    }
+
+    /// Returns the ICCCM WM_STATE we set on the window, if any.
+    pub fn get_wm_state(&self, window_id: &WindowId) -> Option<WmState> {
+        let atom = self.atoms.WM_STATE;
+        let reply = xcb::get_property(&self.conn, false, window_id.to_x(), atom, atom, 0, 1)
+            .get_reply()
+            .ok()?;
+        reply
+            .value::<u32>()
+            .first()
+            .and_then(|&state| WmState::from_x(state))
+    }
+
+    /// Returns whether the window has set the urgency hint in ICCCM's
+    /// WM_HINTS, e.g. because a chat application was sent a message.
+    pub fn is_urgent(&self, window_id: &WindowId) -> bool {
+        self.get_wm_hints(window_id)
+            .is_some_and(|hints| hints.flags & WmHints::URGENCY_HINT != 0)
+    }
+
+    /// Returns the window that this window is transient for (ICCCM's
+    /// WM_TRANSIENT_FOR), e.g. the main window of an application's dialog.
+    pub fn get_wm_transient_for(&self, window_id: &WindowId) -> Option<WindowId> {
+        xcb::get_property(
+            &self.conn,
+            false,
+            window_id.to_x(),
+            xcb::ATOM_WM_TRANSIENT_FOR,
+            xcb::ATOM_WINDOW,
+            0,
+            1,
+        )
+        .get_reply()
+        .ok()
+        .and_then(|reply| reply.value::<xcb::Window>().first().cloned())
+        .filter(|&window| window != xcb::NONE)
+        .map(WindowId)
+    }
+
+    /// Returns the _NET_WM_USER_TIME of the window: the time of the last user
+    /// interaction with it, or 0 if it doesn't want focus when mapped.
+    ///
+    /// This follows _NET_WM_USER_TIME_WINDOW if it is set. Returns `None` if
+    /// the window has no user time.
+    pub fn get_wm_user_time(&self, window_id: &WindowId) -> Option<Timestamp> {
+        let time_window = ewmh::get_wm_user_time_window(&self.conn, window_id.to_x())
+            .get_reply()
+            .unwrap_or_else(|_| window_id.to_x());
+        ewmh::get_wm_user_time(&self.conn, time_window)
+            .get_reply()
+            .ok()
+    }
+
+    /// Returns the _NET_WM_PID of the window: the ID of the process which owns
+    /// it, if it is running on this machine.
+    pub fn get_wm_pid(&self, window_id: &WindowId) -> Option<u32> {
+        ewmh::get_wm_pid(&self.conn, window_id.to_x())
+            .get_reply()
+            .ok()
+    }
+
+    /// Adds or removes one of the window's states in its _NET_WM_STATE,
+    /// leaving any others untouched.
+    pub fn set_window_state(&self, window_id: &WindowId, state: WindowState, enabled: bool) {
+        let atom = match self.window_state_lookup.iter().find(|&(_, s)| *s == state) {
+            Some((atom, _)) => *atom,
+            None => return,
+        };
+        let mut atoms = ewmh::get_wm_state(&self.conn, window_id.to_x())
+            .get_reply()
+            .map(|reply| reply.atoms().to_vec())
+            .unwrap_or_else(|_| Vec::new());
+        atoms.retain(|a| *a != atom);
+        if enabled {
+            atoms.push(atom);
+        }
+        ewmh::set_wm_state(&self.conn, window_id.to_x(), &atoms);
+    }
+
     pub fn get_strut_partial(&self, window_id: &WindowId) -> Option<StrutPartial> {
         ewmh::get_wm_strut_partial(&self.conn, window_id.to_x())
             .get_reply()
             .ok()
     }
 
+    /// Returns the window's _NET_WM_STRUT, which older docks set instead of
+    /// _NET_WM_STRUT_PARTIAL.
+    pub fn get_strut(&self, window_id: &WindowId) -> Option<Strut> {
+        ewmh::get_wm_strut(&self.conn, window_id.to_x())
+            .get_reply()
+            .ok()
+    }
+
     /// Closes a window.
     ///
     /// The window will be closed gracefully using the ICCCM WM_DELETE_WINDOW
@@ -298,8 +1052,117 @@ impl Connection {
         }
     }
 
-    /// Sets the window's position and size.
+    /// Moves and resizes the window.
+    ///
+    /// The width and height include the window's border, if we draw one. The
+    /// size is constrained by the window's WM_NORMAL_HINTS, and if that leaves
+    /// it smaller than requested it is centered in the requested area.
+    ///
+    /// The window is also sent a synthetic ConfigureNotify with its final
+    /// geometry, as ICCCM requires.
     pub fn configure_window(&self, window_id: &WindowId, x: u32, y: u32, width: u32, height: u32) {
+        let hints = self.get_wm_normal_hints(window_id);
+        let (x, y, width, height) =
+            fit_window(x, y, width, height, self.border_width.get(), hints.as_ref());
+        let values = [
+            (xcb::CONFIG_WINDOW_X as u16, x),
+            (xcb::CONFIG_WINDOW_Y as u16, y),
+            (xcb::CONFIG_WINDOW_WIDTH as u16, width),
+            (xcb::CONFIG_WINDOW_HEIGHT as u16, height),
+        ];
+        xcb::configure_window(&self.conn, window_id.to_x(), &values);
+        self.send_configure_notify(window_id, x, y, width, height);
+    }
+
+    /// Sends the window a synthetic ConfigureNotify, telling it its geometry
+    /// (excluding its border).
+    ///
+    /// Clients can't rely on the real ConfigureNotify when a window manager
+    /// is running (e.g. it isn't sent if the window was only moved, or the
+    /// position may be relative to a frame), so ICCCM requires us to send one
+    /// whenever we move or resize a window.
+    pub fn send_configure_notify(
+        &self,
+        window_id: &WindowId,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) {
+        let event = xcb::ConfigureNotifyEvent::new(
+            window_id.to_x(),
+            window_id.to_x(),
+            xcb::NONE,
+            x as i16,
+            y as i16,
+            width as u16,
+            height as u16,
+            self.border_width.get() as u16,
+            false,
+        );
+        xcb::send_event(
+            &self.conn,
+            false,
+            window_id.to_x(),
+            xcb::EVENT_MASK_STRUCTURE_NOTIFY,
+            &event,
+        );
+    }
+
+    /// Sets the width of the borders drawn around windows, which
+    /// `configure_window()` will leave room for.
+    pub fn set_border_width(&self, width: u32) {
+        self.border_width.set(width);
+    }
+
+    pub fn set_window_border_width(&self, window_id: &WindowId, width: u32) {
+        let values = [(xcb::CONFIG_WINDOW_BORDER_WIDTH as u16, width)];
+        xcb::configure_window(&self.conn, window_id.to_x(), &values);
+    }
+
+    /// Sets the color of the window's border, as a `0xRRGGBB` pixel value.
+    pub fn set_window_border_color(&self, window_id: &WindowId, color: u32) {
+        let values = [(xcb::CW_BORDER_PIXEL, color)];
+        xcb::change_window_attributes(&self.conn, window_id.to_x(), &values);
+    }
+
+    /// Sets _NET_FRAME_EXTENTS, telling the window how thick the decorations
+    /// we've drawn around it are on each side.
+    pub fn set_frame_extents(
+        &self,
+        window_id: &WindowId,
+        left: u32,
+        right: u32,
+        top: u32,
+        bottom: u32,
+    ) {
+        ewmh::set_frame_extents(&self.conn, window_id.to_x(), left, right, top, bottom);
+    }
+
+    /// Raises the window to the top of the stacking order, or lowers it to
+    /// the bottom.
+    pub fn restack_window(&self, window_id: &WindowId, raise: bool) {
+        let stack_mode = if raise {
+            xcb::STACK_MODE_ABOVE
+        } else {
+            xcb::STACK_MODE_BELOW
+        };
+        let values = [(xcb::CONFIG_WINDOW_STACK_MODE as u16, stack_mode)];
+        xcb::configure_window(&self.conn, window_id.to_x(), &values);
+    }
+
+    /// Moves and resizes the window to exactly the given geometry, which
+    /// excludes its border.
+    ///
+    /// Unlike `configure_window()`, this doesn't apply the window's size hints.
+    pub fn configure_window_exact(
+        &self,
+        window_id: &WindowId,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) {
         let values = [
             (xcb::CONFIG_WINDOW_X as u16, x),
             (xcb::CONFIG_WINDOW_Y as u16, y),
@@ -307,15 +1170,81 @@ impl Connection {
             (xcb::CONFIG_WINDOW_HEIGHT as u16, height),
         ];
         xcb::configure_window(&self.conn, window_id.to_x(), &values);
+        self.send_configure_notify(window_id, x, y, width, height);
+    }
+
+    /// Returns the window's geometry including its border, i.e. the area that
+    /// `configure_window()` would need to be given to keep it where it is.
+    pub fn get_window_outer_rect(&self, window_id: &WindowId) -> Option<(u32, u32, u32, u32)> {
+        let reply = xcb::get_geometry(&self.conn, window_id.to_x())
+            .get_reply()
+            .ok()?;
+        let border = u32::from(reply.border_width()) * 2;
+        Some((
+            cmp::max(reply.x(), 0) as u32,
+            cmp::max(reply.y(), 0) as u32,
+            u32::from(reply.width()) + border,
+            u32::from(reply.height()) + border,
+        ))
     }
 
-    /// Get's the window's width and height.
-    pub fn get_window_geometry(&self, window_id: &WindowId) -> (u32, u32) {
+    /// Gets the window's position and size, excluding its border, as `(x, y,
+    /// width, height)`.
+    ///
+    /// Positions left of or above the screen are clamped to zero.
+    pub fn get_window_rect(&self, window_id: &WindowId) -> Option<(u32, u32, u32, u32)> {
         let reply = xcb::get_geometry(&self.conn, window_id.to_x())
             .get_reply()
-            .unwrap();
+            .ok()?;
+        Some((
+            cmp::max(reply.x(), 0) as u32,
+            cmp::max(reply.y(), 0) as u32,
+            u32::from(reply.width()),
+            u32::from(reply.height()),
+        ))
+    }
+
+    /// Returns the position of the pointer on the root window, or `None` if
+    /// it's on another screen.
+    pub fn get_pointer_position(&self) -> Option<(i16, i16)> {
+        let reply = xcb::query_pointer(&self.conn, self.root.to_x())
+            .get_reply()
+            .ok()?;
+        if reply.same_screen() {
+            Some((reply.root_x(), reply.root_y()))
+        } else {
+            None
+        }
+    }
+
+    /// Moves the pointer to the center of the window, unless the window has
+    /// been destroyed.
+    pub fn warp_pointer_to_window(&self, window_id: &WindowId) {
+        let (width, height) = match self.get_window_geometry(window_id) {
+            Some(size) => size,
+            None => return,
+        };
+        xcb::warp_pointer(
+            &self.conn,
+            xcb::NONE,
+            window_id.to_x(),
+            0,
+            0,
+            0,
+            0,
+            (width / 2) as i16,
+            (height / 2) as i16,
+        );
+    }
+
+    /// Get's the window's width and height, or `None` if the window has been
+    /// destroyed.
+    pub fn get_window_geometry(&self, window_id: &WindowId) -> Option<(u32, u32)> {
+        let reply = xcb::get_geometry(&self.conn, window_id.to_x())
+            .get_reply()
+            .ok()?;
         // Cast as everywhere else uses u32.
-        (u32::from(reply.width()), u32::from(reply.height()))
+        Some((u32::from(reply.width()), u32::from(reply.height())))
     }
 
     /// Map a window.
@@ -328,6 +1257,62 @@ impl Connection {
         xcb::unmap_window(&self.conn, window_id.to_x());
     }
 
+    /// Synthesizes a press (or release) of the key for `keysym`, using the
+    /// XTEST extension.
+    ///
+    /// This is intended for driving keybindings from tests against a nested X
+    /// server, and isn't used at runtime.
+    #[cfg(feature = "xtest")]
+    pub fn send_fake_key(&self, keysym: xcb::Keysym, press: bool) -> Result<()> {
+        let key_symbols = KeySymbols::new(&self.conn);
+        let keycode = key_symbols
+            .get_keycode(keysym)
+            .next()
+            .ok_or_else(|| format_err!("Failed to get keycode for keysym {}", keysym))?;
+        let type_ = if press {
+            xcb::KEY_PRESS
+        } else {
+            xcb::KEY_RELEASE
+        };
+        xcb::test::fake_input(
+            &self.conn,
+            type_,
+            keycode,
+            xcb::CURRENT_TIME,
+            xcb::NONE,
+            0,
+            0,
+            0,
+        )
+        .request_check()?;
+        Ok(())
+    }
+
+    /// Grabs the keyboard, so that we receive all key events until
+    /// `ungrab_keyboard()` is called.
+    pub fn grab_keyboard(&self) -> Result<()> {
+        let reply = xcb::grab_keyboard(
+            &self.conn,
+            false,
+            self.root.to_x(),
+            xcb::CURRENT_TIME,
+            xcb::GRAB_MODE_ASYNC as u8,
+            xcb::GRAB_MODE_ASYNC as u8,
+        )
+        .get_reply()?;
+        if reply.status() != xcb::GRAB_STATUS_SUCCESS as u8 {
+            return Err(format_err!(
+                "Could not grab keyboard: status {}",
+                reply.status()
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn ungrab_keyboard(&self) {
+        xcb::ungrab_keyboard(&self.conn, xcb::CURRENT_TIME);
+    }
+
     /// Registers for key events.
     ///
     /// If it fails to register any of the keys, it will log an error and continue.
@@ -336,15 +1321,19 @@ impl Connection {
         for key in key_handlers.key_combos() {
             match key_symbols.get_keycode(key.keysym).next() {
                 Some(keycode) => {
-                    xcb::grab_key(
-                        &self.conn,
-                        false,
-                        window_id.to_x(),
-                        key.mod_mask as u16,
-                        keycode,
-                        xcb::GRAB_MODE_ASYNC as u8,
-                        xcb::GRAB_MODE_ASYNC as u8,
-                    );
+                    // Grab the key with each combination of lock modifiers,
+                    // so that bindings work with CapsLock or NumLock on.
+                    for locks in &ModKey::lock_combinations() {
+                        xcb::grab_key(
+                            &self.conn,
+                            false,
+                            window_id.to_x(),
+                            (key.mod_mask | locks) as u16,
+                            keycode,
+                            xcb::GRAB_MODE_ASYNC as u8,
+                            xcb::GRAB_MODE_ASYNC as u8,
+                        );
+                    }
                 }
                 None => {
                     error!(
@@ -356,10 +1345,75 @@ impl Connection {
         }
     }
 
+    /// Grabs the button combo within the window, so that we receive its
+    /// presses and releases and the pointer's motion while it's held.
+    pub fn grab_button(&self, window_id: &WindowId, button: &ButtonCombo) {
+        // As for keys, grab the button with each combination of lock
+        // modifiers.
+        for locks in &ModKey::lock_combinations() {
+            xcb::grab_button(
+                &self.conn,
+                false,
+                window_id.to_x(),
+                (xcb::EVENT_MASK_BUTTON_PRESS
+                    | xcb::EVENT_MASK_BUTTON_RELEASE
+                    | xcb::EVENT_MASK_BUTTON_MOTION) as u16,
+                xcb::GRAB_MODE_ASYNC as u8,
+                xcb::GRAB_MODE_ASYNC as u8,
+                xcb::NONE,
+                xcb::NONE,
+                button.button,
+                (button.mod_mask | locks) as u16,
+            );
+        }
+    }
+
+    /// Grabs clicks of the first mouse button (with any modifiers) within
+    /// the window, for click-to-focus.
+    ///
+    /// The pointer is frozen after each click until `replay_click()` passes
+    /// the click on to the window.
+    pub fn grab_click(&self, window_id: &WindowId) {
+        xcb::grab_button(
+            &self.conn,
+            false,
+            window_id.to_x(),
+            xcb::EVENT_MASK_BUTTON_PRESS as u16,
+            xcb::GRAB_MODE_SYNC as u8,
+            xcb::GRAB_MODE_ASYNC as u8,
+            xcb::NONE,
+            xcb::NONE,
+            xcb::BUTTON_INDEX_1 as u8,
+            xcb::MOD_MASK_ANY as u16,
+        );
+    }
+
+    /// Releases the grab made by `grab_click()`.
+    pub fn ungrab_click(&self, window_id: &WindowId) {
+        xcb::ungrab_button(
+            &self.conn,
+            xcb::BUTTON_INDEX_1 as u8,
+            window_id.to_x(),
+            xcb::MOD_MASK_ANY as u16,
+        );
+    }
+
+    /// Passes the click which caused an `Event::Click` on to the window it
+    /// was in, and unfreezes the pointer.
+    pub fn replay_click(&self) {
+        xcb::allow_events(
+            &self.conn,
+            xcb::ALLOW_REPLAY_POINTER as u8,
+            xcb::CURRENT_TIME,
+        );
+    }
+
     pub fn enable_window_tracking(&self, window_id: &WindowId) {
         let values = [(
             xcb::CW_EVENT_MASK,
-            xcb::EVENT_MASK_ENTER_WINDOW | xcb::EVENT_MASK_STRUCTURE_NOTIFY,
+            xcb::EVENT_MASK_ENTER_WINDOW
+                | xcb::EVENT_MASK_STRUCTURE_NOTIFY
+                | xcb::EVENT_MASK_PROPERTY_CHANGE,
         )];
         xcb::change_window_attributes(&self.conn, window_id.to_x(), &values);
     }
@@ -369,10 +1423,16 @@ impl Connection {
         xcb::change_window_attributes(&self.conn, window_id.to_x(), &values);
     }
 
+    /// Sets where focus reverts to when a window focused by `focus_window()`
+    /// goes away.
+    pub fn set_focus_revert(&self, focus_revert: FocusRevert) {
+        self.focus_revert.set(focus_revert);
+    }
+
     pub fn focus_window(&self, window_id: &WindowId) {
         xcb::set_input_focus(
             &self.conn,
-            xcb::INPUT_FOCUS_POINTER_ROOT as u8,
+            self.focus_revert.get().to_x(),
             window_id.to_x(),
             xcb::CURRENT_TIME,
         );
@@ -384,18 +1444,304 @@ impl Connection {
         ewmh::set_active_window(&self.conn, self.screen_idx, xcb::NONE);
     }
 
+    /// Restarts the X server's idle timer, as if the user had just used the
+    /// keyboard or mouse, deactivating the screen saver if it is active.
+    ///
+    /// The server also uses the idle timer to decide when to turn the screen
+    /// off with DPMS.
+    pub fn reset_screen_saver(&self) {
+        xcb::force_screen_saver(&self.conn, xcb::SCREEN_SAVER_RESET as u8);
+    }
+
+    /// Makes the `EventLoop` yield `Event::Timer` every `interval`, or stops
+    /// it if `interval` is `None`.
+    pub fn set_timer(&self, interval: Option<Duration>) {
+        self.timer.set(interval);
+    }
+
+    /// Sets EWMH's _NET_SHOWING_DESKTOP to indicate whether we're showing the
+    /// desktop.
+    pub fn set_showing_desktop(&self, showing: bool) {
+        ewmh::set_showing_desktop(&self.conn, self.screen_idx, showing as u32);
+    }
+
+    /// Creates a window at the given position and size showing a copy of
+    /// `source`'s contents, scaled to fit, above every other window.
+    ///
+    /// The source is redirected offscreen (using XCOMPOSITE), so that its
+    /// contents are available even while other windows cover it. The
+    /// thumbnail must be redrawn with `update_thumbnail()` whenever an
+    /// `Event::DamageNotify` arrives for the source, and rescaled with
+    /// `rescale_thumbnail()` whenever an `Event::ConfigureNotify` does.
+    #[cfg(feature = "pip")]
+    pub fn create_thumbnail(
+        &self,
+        source: &WindowId,
+        x: i16,
+        y: i16,
+        width: u16,
+        height: u16,
+    ) -> Result<Thumbnail> {
+        let conn = &self.conn;
+        xcb::composite::query_version(conn, 0, 4)
+            .get_reply()
+            .context("XCOMPOSITE is not available")?;
+        xcb::damage::query_version(conn, 1, 1)
+            .get_reply()
+            .context("DAMAGE is not available")?;
+        let formats = xcb::render::query_pict_formats(conn)
+            .get_reply()
+            .context("RENDER is not available")?;
+
+        let source_visual = xcb::get_window_attributes(conn, source.to_x())
+            .get_reply()?
+            .visual();
+        let root_visual = conn
+            .get_setup()
+            .roots()
+            .nth(self.screen_idx as usize)
+            .ok_or_else(|| format_err!("Invalid screen"))?
+            .root_visual();
+        let source_format = find_visual_format(&formats, source_visual)
+            .ok_or_else(|| format_err!("No picture format for window: {}", source))?;
+        let format = find_visual_format(&formats, root_visual)
+            .ok_or_else(|| format_err!("No picture format for the root window"))?;
+
+        xcb::composite::redirect_window_checked(
+            conn,
+            source.to_x(),
+            xcb::composite::REDIRECT_AUTOMATIC as u8,
+        )
+        .request_check()?;
+
+        // Override-redirect, so that we don't try to manage it ourselves.
+        let window = conn.generate_id();
+        xcb::create_window(
+            conn,
+            xcb::COPY_FROM_PARENT as u8,
+            window,
+            self.root.to_x(),
+            x,
+            y,
+            width,
+            height,
+            0,
+            xcb::WINDOW_CLASS_INPUT_OUTPUT as u16,
+            xcb::COPY_FROM_PARENT,
+            &[(xcb::CW_OVERRIDE_REDIRECT, 1)],
+        );
+        xcb::map_window(conn, window);
+
+        let source_picture = conn.generate_id();
+        xcb::render::create_picture(
+            conn,
+            source_picture,
+            source.to_x(),
+            source_format,
+            &[(
+                xcb::render::CP_SUBWINDOW_MODE,
+                xcb::SUBWINDOW_MODE_INCLUDE_INFERIORS,
+            )],
+        );
+        xcb::render::set_picture_filter(conn, source_picture, "bilinear", &[]);
+
+        let picture = conn.generate_id();
+        xcb::render::create_picture(conn, picture, window, format, &[]);
+
+        let damage = conn.generate_id();
+        xcb::damage::create(
+            conn,
+            damage,
+            source.to_x(),
+            xcb::damage::REPORT_LEVEL_NON_EMPTY as u8,
+        );
+
+        let thumbnail = Thumbnail {
+            source: *source,
+            window: WindowId(window),
+            damage,
+            source_picture,
+            picture,
+            width,
+            height,
+        };
+        if !self.rescale_thumbnail(&thumbnail) {
+            self.destroy_thumbnail(thumbnail);
+            return Err(format_err!("Window has gone: {}", source));
+        }
+        Ok(thumbnail)
+    }
+
+    /// Scales the thumbnail's source to the size of the thumbnail, and
+    /// redraws it. This must be called whenever an `Event::ConfigureNotify`
+    /// arrives for the source, as its size may have changed.
+    ///
+    /// Returns `false`, without redrawing, if the source has been destroyed.
+    #[cfg(feature = "pip")]
+    pub fn rescale_thumbnail(&self, thumbnail: &Thumbnail) -> bool {
+        // The transform maps points in the thumbnail to points in the source.
+        let (source_width, source_height) = match self.get_window_rect(&thumbnail.source) {
+            Some((_, _, width, height)) => (width, height),
+            None => return false,
+        };
+        let scale_x = fixed(f64::from(source_width) / f64::from(thumbnail.width.max(1)));
+        let scale_y = fixed(f64::from(source_height) / f64::from(thumbnail.height.max(1)));
+        let one = fixed(1.0);
+        let transform = xcb::render::Transform::new(scale_x, 0, 0, 0, scale_y, 0, 0, 0, one);
+        xcb::render::set_picture_transform(&self.conn, thumbnail.source_picture, transform);
+        self.update_thumbnail(thumbnail);
+        true
+    }
+
+    /// Redraws the thumbnail from its source's current contents, and raises
+    /// it back above any windows which have been raised since.
+    #[cfg(feature = "pip")]
+    pub fn update_thumbnail(&self, thumbnail: &Thumbnail) {
+        // Acknowledge the damage, so that we're told about the next change.
+        xcb::damage::subtract(&self.conn, thumbnail.damage, xcb::NONE, xcb::NONE);
+        xcb::render::composite(
+            &self.conn,
+            xcb::render::PICT_OP_SRC as u8,
+            thumbnail.source_picture,
+            xcb::NONE,
+            thumbnail.picture,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            thumbnail.width,
+            thumbnail.height,
+        );
+        self.restack_window(&thumbnail.window, true);
+    }
+
+    /// Destroys the thumbnail, and stops redirecting its source.
+    #[cfg(feature = "pip")]
+    pub fn destroy_thumbnail(&self, thumbnail: Thumbnail) {
+        xcb::damage::destroy(&self.conn, thumbnail.damage);
+        xcb::render::free_picture(&self.conn, thumbnail.source_picture);
+        xcb::render::free_picture(&self.conn, thumbnail.picture);
+        xcb::destroy_window(&self.conn, thumbnail.window.to_x());
+        xcb::composite::unredirect_window(
+            &self.conn,
+            thumbnail.source.to_x(),
+            xcb::composite::REDIRECT_AUTOMATIC as u8,
+        );
+    }
+
     pub fn get_event_loop(&self) -> EventLoop<'_> {
-        EventLoop { connection: self }
+        EventLoop {
+            connection: self,
+            pending: None,
+            queued: VecDeque::new(),
+            timer_due: None,
+        }
     }
 }
 
+/// Fits a window with a border `border_width` pixels wide into the area at
+/// `(x, y, width, height)`, returning the geometry to give it excluding its
+/// border.
+///
+/// The window is kept within its size hints, centered in the area if they
+/// make it smaller.
+fn fit_window(
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    border_width: u32,
+    hints: Option<&SizeHints>,
+) -> (u32, u32, u32, u32) {
+    let border = border_width * 2;
+    let (inner_width, inner_height) = (width.saturating_sub(border), height.saturating_sub(border));
+    let (inner_width, inner_height) = match hints {
+        Some(hints) => hints.constrain(inner_width, inner_height),
+        None => (inner_width, inner_height),
+    };
+    let x = x + width.saturating_sub(inner_width + border) / 2;
+    let y = y + height.saturating_sub(inner_height + border) / 2;
+    (x, y, inner_width, inner_height)
+}
+
+/// Decodes the value of a text property: Latin-1 if `latin1` (i.e. if its type
+/// is STRING), and otherwise UTF-8. Returns `None` if it isn't valid UTF-8.
+fn decode_text(bytes: &[u8], latin1: bool) -> Option<String> {
+    if latin1 {
+        Some(bytes.iter().map(|&b| char::from(b)).collect())
+    } else {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+/// Converts `value` to RENDER's 16.16 fixed point representation.
+#[cfg(feature = "pip")]
+fn fixed(value: f64) -> xcb::render::Fixed {
+    (value * 65536.0) as xcb::render::Fixed
+}
+
+/// Finds the RENDER picture format matching `visual`.
+#[cfg(feature = "pip")]
+fn find_visual_format(
+    formats: &xcb::render::QueryPictFormatsReply,
+    visual: xcb::Visualid,
+) -> Option<xcb::render::Pictformat> {
+    for screen in formats.screens() {
+        for depth in screen.depths() {
+            if let Some(found) = depth.visuals().find(|v| v.visual() == visual) {
+                return Some(found.format());
+            }
+        }
+    }
+    None
+}
+
 /// Events received from the `EventLoop`.
 pub enum Event {
-    MapRequest(WindowId),
+    /// Windows asked to be mapped. MapRequests which arrive in a burst (e.g.
+    /// when an application opens several windows at once) are batched
+    /// together.
+    MapRequest(Vec<WindowId>),
     UnmapNotify(WindowId),
     DestroyNotify(WindowId),
-    KeyPress(KeyCombo),
+    KeyPress(KeyCombo, Timestamp),
+    KeyRelease(KeyCombo, Timestamp),
     EnterNotify(WindowId),
+    /// One of the window's properties was changed or deleted.
+    PropertyNotify(WindowId, Atom),
+    /// A grabbed button was pressed over a top-level window, with the
+    /// pointer at the given position on the root window.
+    ButtonPress(ButtonCombo, WindowId, i16, i16),
+    ButtonRelease,
+    /// The first mouse button was pressed in a window grabbed by
+    /// `Connection::grab_click()`. The click must be passed on with
+    /// `Connection::replay_click()`.
+    Click(WindowId),
+    /// The pointer moved to the given position on the root window, while a
+    /// grabbed button was held.
+    MotionNotify(i16, i16),
+    ShowDesktopRequest(bool),
+    /// A client asked to move or resize its window, and we allowed it.
+    ConfigureRequest(WindowId),
+    /// A client asked for one of its window's _NET_WM_STATE states to change.
+    StateRequest(WindowId, WindowState, StateAction),
+    /// A client (e.g. a pager) asked to switch to the desktop at this index,
+    /// with _NET_CURRENT_DESKTOP.
+    CurrentDesktopRequest(usize),
+    /// A client (e.g. a taskbar, or the window's own application) asked for a
+    /// window to be activated, with _NET_ACTIVE_WINDOW.
+    ActivateRequest(WindowId),
+    /// The interval given to `Connection::set_timer()` has passed.
+    Timer,
+    /// The contents of a window we're showing a `Thumbnail` of have changed.
+    #[cfg(feature = "pip")]
+    DamageNotify(WindowId),
+    /// A top-level window was moved or resized, e.g. a window we're showing a
+    /// `Thumbnail` of.
+    #[cfg(feature = "pip")]
+    ConfigureNotify(WindowId),
 }
 
 /// An iterator that yields events from the X event loop.
@@ -403,6 +1749,19 @@ pub enum Event {
 /// Use `Connection::get_event_loop()` to get one.
 pub struct EventLoop<'a> {
     connection: &'a Connection,
+    // An event we've read from the queue but not yet processed.
+    pending: Option<xcb::GenericEvent>,
+    // Events we've translated but not yet yielded, for X events which
+    // translate into more than one `Event`.
+    queued: VecDeque<Event>,
+    // When to next yield `Event::Timer`, if the timer is set.
+    timer_due: Option<Instant>,
+}
+
+/// Why `EventLoop::wait_for_event()` stopped waiting.
+enum Wakeup {
+    Event(xcb::GenericEvent),
+    Timer,
 }
 
 impl<'a> Iterator for EventLoop<'a> {
@@ -414,20 +1773,44 @@ impl<'a> Iterator for EventLoop<'a> {
             // have) just yielded.
             self.connection.flush();
 
-            let event = self
-                .connection
-                .conn
-                .wait_for_event()
-                .expect("wait_for_event() returned None: IO error?");
+            if QUIT_REQUESTED.load(Ordering::SeqCst) {
+                return None;
+            }
+
+            if let Some(event) = self.queued.pop_front() {
+                return Some(event);
+            }
+
+            let event = match self.pending.take() {
+                Some(event) => event,
+                None => match self.wait_for_event()? {
+                    Wakeup::Event(event) => event,
+                    Wakeup::Timer => return Some(Event::Timer),
+                },
+            };
 
             unsafe {
-                let propagate = match event.response_type() {
+                // Events sent with SendEvent (e.g. EWMH client messages) have
+                // the high bit set.
+                let propagate = match event.response_type() & !0x80 {
                     xcb::CONFIGURE_REQUEST => self.on_configure_request(xcb::cast_event(&event)),
                     xcb::MAP_REQUEST => self.on_map_request(xcb::cast_event(&event)),
                     xcb::UNMAP_NOTIFY => self.on_unmap_notify(xcb::cast_event(&event)),
                     xcb::DESTROY_NOTIFY => self.on_destroy_notify(xcb::cast_event(&event)),
                     xcb::KEY_PRESS => self.on_key_press(xcb::cast_event(&event)),
+                    xcb::KEY_RELEASE => self.on_key_release(xcb::cast_event(&event)),
                     xcb::ENTER_NOTIFY => self.on_enter_notify(xcb::cast_event(&event)),
+                    xcb::PROPERTY_NOTIFY => self.on_property_notify(xcb::cast_event(&event)),
+                    xcb::BUTTON_PRESS => self.on_button_press(xcb::cast_event(&event)),
+                    xcb::BUTTON_RELEASE => Some(Event::ButtonRelease),
+                    xcb::MOTION_NOTIFY => self.on_motion_notify(xcb::cast_event(&event)),
+                    xcb::CLIENT_MESSAGE => self.on_client_message(xcb::cast_event(&event)),
+                    #[cfg(feature = "pip")]
+                    xcb::CONFIGURE_NOTIFY => self.on_configure_notify(xcb::cast_event(&event)),
+                    #[cfg(feature = "pip")]
+                    response_type if self.is_damage_notify(response_type) => {
+                        self.on_damage_notify(xcb::cast_event(&event))
+                    }
                     _ => None,
                 };
 
@@ -440,8 +1823,64 @@ impl<'a> Iterator for EventLoop<'a> {
 }
 
 impl<'a> EventLoop<'a> {
+    /// Blocks until the next event arrives or the timer is due, or returns
+    /// `None` if we're asked to quit (e.g. by a signal interrupting the wait).
+    fn wait_for_event(&mut self) -> Option<Wakeup> {
+        let conn = &self.connection.conn;
+        loop {
+            if QUIT_REQUESTED.load(Ordering::SeqCst) {
+                return None;
+            }
+            if let Some(event) = conn.poll_for_event() {
+                return Some(Wakeup::Event(event));
+            }
+            if conn.has_error().is_err() {
+                panic!("X connection failed: IO error?");
+            }
+
+            let now = Instant::now();
+            self.timer_due = match self.connection.timer.get() {
+                Some(interval) => Some(self.timer_due.unwrap_or(now + interval)),
+                None => None,
+            };
+            let timeout = match self.timer_due {
+                Some(due) if due <= now => {
+                    self.timer_due = None;
+                    return Some(Wakeup::Timer);
+                }
+                // Round up, so that we don't wake just before the timer is due
+                // and then spin until it is.
+                Some(due) => {
+                    cmp::min((due - now).as_micros().div_ceil(1000), i32::MAX as u128) as i32
+                }
+                None => -1,
+            };
+
+            // Also wait on the quit pipe, so that a signal which arrives
+            // after we checked QUIT_REQUESTED above still wakes us. (poll()
+            // returning EINTR alone would miss it.) A negative fd is ignored.
+            let quit_fd = QUIT_PIPE_READ.load(Ordering::SeqCst);
+            let mut fds = [
+                libc::pollfd {
+                    fd: conn.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                },
+                libc::pollfd {
+                    fd: quit_fd,
+                    events: libc::POLLIN,
+                    revents: 0,
+                },
+            ];
+            unsafe {
+                libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout);
+            }
+        }
+    }
+
     fn on_configure_request(&self, event: &xcb::ConfigureRequestEvent) -> Option<Event> {
-        // This request is not interesting for us: grant it unchanged.
+        // Grant the request unchanged. Tiled windows will be put back in place
+        // the next time their group is laid out.
         // Build a request with all attributes set, then filter out to only include
         // those from the original request.
         let values = vec![
@@ -453,7 +1892,7 @@ impl<'a> EventLoop<'a> {
                 xcb::CONFIG_WINDOW_BORDER_WIDTH as u16,
                 u32::from(event.border_width()),
             ),
-            (xcb::CONFIG_WINDOW_SIBLING as u16, event.sibling() as u32),
+            (xcb::CONFIG_WINDOW_SIBLING as u16, event.sibling()),
             (
                 xcb::CONFIG_WINDOW_STACK_MODE as u16,
                 u32::from(event.stack_mode()),
@@ -465,12 +1904,23 @@ impl<'a> EventLoop<'a> {
             .collect();
         xcb::configure_window(&self.connection.conn, event.window(), &filtered_values);
 
-        // There's no value in propogating this event.
-        None
+        // Floating windows keep the geometry they last asked for.
+        Some(Event::ConfigureRequest(WindowId(event.window())))
     }
 
-    fn on_map_request(&self, event: &xcb::MapRequestEvent) -> Option<Event> {
-        Some(Event::MapRequest(WindowId(event.window())))
+    fn on_map_request(&mut self, event: &xcb::MapRequestEvent) -> Option<Event> {
+        let mut window_ids = vec![WindowId(event.window())];
+        // Drain any other MapRequests which have already arrived, so that they
+        // can be handled with a single layout.
+        while let Some(next) = self.connection.conn.poll_for_event() {
+            if next.response_type() & !0x80 != xcb::MAP_REQUEST {
+                self.pending = Some(next);
+                break;
+            }
+            let next: &xcb::MapRequestEvent = unsafe { xcb::cast_event(&next) };
+            window_ids.push(WindowId(next.window()));
+        }
+        Some(Event::MapRequest(window_ids))
     }
 
     fn on_unmap_notify(&self, event: &xcb::UnmapNotifyEvent) -> Option<Event> {
@@ -492,12 +1942,216 @@ impl<'a> EventLoop<'a> {
     fn on_key_press(&self, event: &xcb::KeyPressEvent) -> Option<Event> {
         let key_symbols = KeySymbols::new(&self.connection.conn);
         let keysym = key_symbols.press_lookup_keysym(event, 0);
-        let mod_mask = u32::from(event.state());
-        let key = KeyCombo { mod_mask, keysym };
-        Some(Event::KeyPress(key))
+        let key = KeyCombo::from_event(event.state(), keysym);
+        Some(Event::KeyPress(key, event.time()))
+    }
+
+    fn on_key_release(&mut self, event: &xcb::KeyReleaseEvent) -> Option<Event> {
+        // When a key auto-repeats, X sends a release immediately followed by a
+        // press with the same keycode and timestamp. Swallow such releases, so
+        // that bindings which fire on release only fire once the key is really
+        // let go.
+        if let Some(next) = self.connection.conn.poll_for_queued_event() {
+            let repeat = next.response_type() == xcb::KEY_PRESS && {
+                let next: &xcb::KeyPressEvent = unsafe { xcb::cast_event(&next) };
+                next.detail() == event.detail() && next.time() == event.time()
+            };
+            self.pending = Some(next);
+            if repeat {
+                return None;
+            }
+        }
+
+        let key_symbols = KeySymbols::new(&self.connection.conn);
+        let keysym = key_symbols.release_lookup_keysym(event, 0);
+        let key = KeyCombo::from_event(event.state(), keysym);
+        Some(Event::KeyRelease(key, event.time()))
     }
 
     fn on_enter_notify(&self, event: &xcb::EnterNotifyEvent) -> Option<Event> {
         Some(Event::EnterNotify(WindowId(event.event())))
     }
+
+    fn on_property_notify(&self, event: &xcb::PropertyNotifyEvent) -> Option<Event> {
+        Some(Event::PropertyNotify(
+            WindowId(event.window()),
+            event.atom(),
+        ))
+    }
+
+    fn on_button_press(&self, event: &xcb::ButtonPressEvent) -> Option<Event> {
+        if event.event() != self.connection.root.to_x() {
+            return Some(Event::Click(WindowId(event.event())));
+        }
+        // Mouse bindings are grabbed on the root window, so the top-level
+        // window under the pointer is the event's child (if there is one).
+        if event.child() == xcb::NONE {
+            return None;
+        }
+        let button = ButtonCombo::from_event(event.state(), event.detail());
+        Some(Event::ButtonPress(
+            button,
+            WindowId(event.child()),
+            event.root_x(),
+            event.root_y(),
+        ))
+    }
+
+    fn on_motion_notify(&mut self, event: &xcb::MotionNotifyEvent) -> Option<Event> {
+        // Skip to the latest of any motion that's queued up, so that a slow
+        // drag doesn't fall behind the pointer.
+        let (mut x, mut y) = (event.root_x(), event.root_y());
+        while let Some(next) = self.connection.conn.poll_for_queued_event() {
+            if next.response_type() != xcb::MOTION_NOTIFY {
+                self.pending = Some(next);
+                break;
+            }
+            let next: &xcb::MotionNotifyEvent = unsafe { xcb::cast_event(&next) };
+            x = next.root_x();
+            y = next.root_y();
+        }
+        Some(Event::MotionNotify(x, y))
+    }
+
+    /// Returns whether events of `response_type` are DAMAGE's DamageNotify,
+    /// whose response type depends on the order extensions were loaded in.
+    #[cfg(feature = "pip")]
+    fn is_damage_notify(&self, response_type: u8) -> bool {
+        self.connection
+            .conn
+            .get_extension_data(xcb::damage::id())
+            .filter(|data| data.present())
+            .is_some_and(|data| response_type == data.first_event() + xcb::damage::NOTIFY)
+    }
+
+    #[cfg(feature = "pip")]
+    fn on_configure_notify(&self, event: &xcb::ConfigureNotifyEvent) -> Option<Event> {
+        // Only take the events from our SUBSTRUCTURE_NOTIFY mask on the root
+        // window, which are still sent while window tracking is disabled
+        // (e.g. while we lay windows out), and skip those we sent ourselves.
+        let synthetic = event.response_type() & 0x80 != 0;
+        if event.event() == self.connection.root_window_id().to_x() && !synthetic {
+            Some(Event::ConfigureNotify(WindowId(event.window())))
+        } else {
+            None
+        }
+    }
+
+    #[cfg(feature = "pip")]
+    fn on_damage_notify(&self, event: &xcb::damage::NotifyEvent) -> Option<Event> {
+        Some(Event::DamageNotify(WindowId(event.drawable())))
+    }
+
+    fn on_client_message(&mut self, event: &xcb::ClientMessageEvent) -> Option<Event> {
+        let conn = &self.connection.conn;
+        let data = event.data().data32();
+        if event.type_() == conn.SHOWING_DESKTOP() {
+            Some(Event::ShowDesktopRequest(data[0] != 0))
+        } else if event.type_() == conn.CURRENT_DESKTOP() {
+            Some(Event::CurrentDesktopRequest(data[0] as usize))
+        } else if event.type_() == conn.ACTIVE_WINDOW() {
+            Some(Event::ActivateRequest(WindowId(event.window())))
+        } else if event.type_() == conn.WM_STATE() {
+            // A single message may change two states at once (e.g. maximized
+            // vertically and horizontally).
+            let action = StateAction::from_x(data[0])?;
+            let lookup = &self.connection.window_state_lookup;
+            let mut events = data[1..3]
+                .iter()
+                .filter_map(|atom| lookup.get(atom))
+                .map(|state| Event::StateRequest(WindowId(event.window()), *state, action));
+            let first = events.next();
+            self.queued.extend(events);
+            first
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode_text() {
+        assert_eq!(decode_text(b"caf\xc3\xa9", false), Some("café".to_owned()));
+        assert_eq!(decode_text(b"caf\xe9", true), Some("café".to_owned()));
+        assert_eq!(decode_text(b"caf\xe9", false), None);
+    }
+
+    #[test]
+    fn test_size_hints_from_values() {
+        let mut values = [0; 18];
+        values[0] = SizeHints::MIN_SIZE | SizeHints::RESIZE_INC;
+        values[5] = 100;
+        values[6] = 50;
+        values[7] = 999;
+        values[9] = 8;
+        values[10] = 16;
+        assert_eq!(
+            SizeHints::from_values(&values),
+            Some(SizeHints {
+                min_size: Some((100, 50)),
+                resize_inc: Some((8, 16)),
+                ..SizeHints::default()
+            })
+        );
+        assert_eq!(SizeHints::from_values(&values[..4]), None);
+    }
+
+    #[test]
+    fn test_size_hints_position() {
+        let mut values = [0; 18];
+        assert!(!SizeHints::from_values(&values).unwrap().position);
+        values[0] = SizeHints::P_POSITION;
+        assert!(SizeHints::from_values(&values).unwrap().position);
+    }
+
+    #[test]
+    fn test_size_hints_constrain_min_max() {
+        let hints = SizeHints {
+            min_size: Some((200, 100)),
+            max_size: Some((400, 300)),
+            ..SizeHints::default()
+        };
+        assert_eq!(hints.constrain(1000, 1000), (400, 300));
+        assert_eq!(hints.constrain(50, 50), (200, 100));
+        assert_eq!(hints.constrain(300, 200), (300, 200));
+    }
+
+    #[test]
+    fn test_size_hints_constrain_resize_inc() {
+        let hints = SizeHints {
+            base_size: Some((4, 2)),
+            resize_inc: Some((10, 20)),
+            ..SizeHints::default()
+        };
+        assert_eq!(hints.constrain(109, 109), (104, 102));
+    }
+
+    #[test]
+    fn test_size_hints_constrain_aspect() {
+        let hints = SizeHints {
+            aspect: Some(((16, 9), (16, 9))),
+            ..SizeHints::default()
+        };
+        assert_eq!(hints.constrain(1600, 1600), (1600, 900));
+        assert_eq!(hints.constrain(3200, 900), (1600, 900));
+    }
+
+    #[test]
+    fn test_fit_window_is_stable_with_border() {
+        let hints = SizeHints {
+            base_size: Some((4, 2)),
+            resize_inc: Some((10, 20)),
+            ..SizeHints::default()
+        };
+        let (x, y, width, height) = fit_window(0, 0, 500, 400, 3, Some(&hints));
+        assert_eq!((x, y, width, height), (0, 6, 494, 382));
+        // Fitting the window to its own area, including its border, (e.g.
+        // when its size is pinned) leaves it unchanged.
+        let refit = fit_window(x, y, width + 6, height + 6, 3, Some(&hints));
+        assert_eq!(refit, (x, y, width, height));
+    }
 }
@@ -0,0 +1,17 @@
+//! Drives keybindings through the XTEST extension.
+//!
+//! These need an X server to send keys to, so are ignored by default. Run
+//! them against a nested server with e.g.:
+//!
+//!     Xephyr :1 & DISPLAY=:1 cargo test --features xtest -- --ignored
+
+#![cfg(feature = "xtest")]
+
+use lanta::keysym;
+
+#[test]
+#[ignore]
+fn test_send_fake_key() {
+    lanta::send_fake_key(keysym::XK_a, true).unwrap();
+    lanta::send_fake_key(keysym::XK_a, false).unwrap();
+}